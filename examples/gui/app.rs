@@ -2,14 +2,22 @@ use std::{
     collections::VecDeque,
     num::NonZeroU16,
     sync::mpsc::{channel, Receiver, Sender},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use eframe::{
     egui::{self, FontDefinitions, FontFamily, ScrollArea, Vec2},
     epi::{self, Storage},
 };
-use panel_protocol::{Command, PulseMode, Report};
+use panel_protocol::{
+    color::{LedConfig, Rgb},
+    discovery::{LightInfo, Targets},
+    pulse,
+    shadow::{LightSetpoint, Shadow},
+    telemetry::Telemetry,
+    units::Kelvin,
+    Address, Command, PulseMode, Report, Target,
+};
 
 const SHOW_LAST_COMMAND_NUM: usize = 15;
 
@@ -18,18 +26,26 @@ struct LedState {
     r: u8,
     g: u8,
     b: u8,
-    if_breathing_interval_ms: u16,
+    interval_ms: u16,
     pulse_mode: PulseMode,
 }
 
 impl Default for LedState {
     fn default() -> Self {
-        Self {
-            r: 255,
-            g: 255,
-            b: 255,
-            if_breathing_interval_ms: 4000,
-            pulse_mode: PulseMode::Solid,
+        Self { r: 255, g: 255, b: 255, interval_ms: 4000, pulse_mode: PulseMode::Solid }
+    }
+}
+
+impl LedState {
+    /// `pulse_mode` with its timing taken from the interval slider.
+    fn timed(&self, pulse_mode: PulseMode) -> PulseMode {
+        let interval_ms = NonZeroU16::new(self.interval_ms).unwrap();
+        match pulse_mode {
+            PulseMode::Breathing { .. } => PulseMode::Breathing { interval_ms },
+            PulseMode::Blink { .. } => PulseMode::Blink { on_ms: interval_ms, off_ms: interval_ms },
+            PulseMode::FadeTo { .. } => PulseMode::FadeTo { ms: interval_ms.get() },
+            PulseMode::ColorCycle { .. } => PulseMode::ColorCycle { period_ms: interval_ms },
+            pulse_mode => pulse_mode,
         }
     }
 }
@@ -45,17 +61,41 @@ impl From<LedState> for Command {
     }
 }
 
-#[derive(Default, Clone, Copy, PartialEq)]
-struct LightState {
-    brightness: u16,
-    temperature: u16,
+/// The lights to show until the panel lists its own, which older firmware never does.
+fn default_targets() -> Targets {
+    let light = |target, name: &str| LightInfo {
+        target,
+        name: name.to_string(),
+        brightness: 0..=u16::MAX,
+        temperature: Kelvin::MIN.get()..=Kelvin::MAX.get(),
+    };
+    Targets {
+        lights: vec![
+            light(Target::FRONT_LIGHTS, "Front Lights"),
+            light(Target::BACK_LIGHTS, "Back Lights"),
+        ],
+        fans: vec![],
+        ring: None,
+    }
 }
 
 pub struct App {
     report_rx: Receiver<Report>,
     command_tx: Sender<Command>,
     led_state: LedState,
-    light_state: [LightState; 2],
+    led_config: LedConfig,
+    /// When the LED was last changed, and the color it showed then, to preview its animation.
+    led_changed_at: Instant,
+    led_previous: Rgb,
+    last_dial_turn: Option<Instant>,
+    dial_position: i32,
+    targets: Targets,
+    /// Targets the panel is in the middle of listing.
+    listed_targets: Targets,
+    light_state: Vec<LightSetpoint>,
+    fan_speeds: Vec<u16>,
+    telemetry: Telemetry,
+    shadow: Shadow,
     last_recv_reports: VecDeque<Report>,
     kill_updater: Option<Sender<()>>,
 }
@@ -66,13 +106,59 @@ impl App {
             report_rx,
             command_tx,
             led_state: Default::default(),
-            light_state: Default::default(),
+            led_config: LedConfig::default(),
+            led_changed_at: Instant::now(),
+            led_previous: Rgb::BLACK,
+            last_dial_turn: None,
+            dial_position: 0,
+            targets: default_targets(),
+            listed_targets: Targets::default(),
+            light_state: Vec::new(),
+            fan_speeds: Vec::new(),
+            telemetry: Telemetry::new(),
+            shadow: Shadow::new(),
             last_recv_reports: VecDeque::new(),
             kill_updater: None,
         }
     }
 
+    /// The color pixel `index` of an LED ring of `pixel_count` pixels should be showing right
+    /// now. The status LED is a ring of one pixel.
+    fn led_preview(&self, index: u16, pixel_count: u16) -> Rgb {
+        let LedState { r, g, b, pulse_mode, .. } = self.led_state;
+        let context = pulse::Context {
+            previous: self.led_previous,
+            since_dial_turn_ms: self.last_dial_turn.map(|at| at.elapsed().as_millis() as u32),
+            dial_position: self.dial_position,
+        };
+        let elapsed_ms = self.led_changed_at.elapsed().as_millis() as u32;
+        pulse::render_pixel(Rgb::new(r, g, b), pulse_mode, elapsed_ms, context, index, pixel_count)
+            .scaled(self.led_config.global_brightness)
+    }
+
     fn led_configuration_section(&mut self, ui: &mut eframe::egui::Ui) {
+        let Rgb { r, g, b } = self.led_preview(0, 1);
+        let (preview, _) =
+            ui.allocate_exact_size(Vec2::new(ui.available_width(), 24.0), egui::Sense::hover());
+        ui.painter().rect_filled(preview, 4.0, egui::Color32::from_rgb(r, g, b));
+
+        if let Some(ring) = &self.targets.ring {
+            let pixel_count = ring.pixel_count;
+            let size = ui.available_width() / f32::from(pixel_count.max(1));
+            let (row, _) =
+                ui.allocate_exact_size(Vec2::new(ui.available_width(), size), egui::Sense::hover());
+            for index in 0..pixel_count {
+                let Rgb { r, g, b } = self.led_preview(index, pixel_count);
+                let offset = Vec2::new(size * f32::from(index), 0.0);
+                let pixel = egui::Rect::from_min_size(row.min + offset, Vec2::splat(size));
+                ui.painter().rect_filled(
+                    pixel.shrink(2.0),
+                    size / 2.0,
+                    egui::Color32::from_rgb(r, g, b),
+                );
+            }
+        }
+
         ui.add(
             egui::Slider::new(&mut self.led_state.r, 0..=255).text("LED Red").clamp_to_range(true),
         );
@@ -85,70 +171,119 @@ impl App {
             egui::Slider::new(&mut self.led_state.b, 0..=255).text("LED Blue").clamp_to_range(true),
         );
 
+        ui.add(
+            egui::Slider::new(&mut self.led_config.global_brightness, 0..=255)
+                .text("LED Global Brightness")
+                .clamp_to_range(true),
+        );
+        ui.add(
+            egui::Slider::new(&mut self.led_config.gamma, LedConfig::LINEAR_GAMMA..=30)
+                .text("LED Gamma (tenths)")
+                .clamp_to_range(true),
+        );
+
         // Pulse mode
+        let modes = [
+            (PulseMode::Solid, "Solid"),
+            (PulseMode::DialTurn, "DialTurn"),
+            (PulseMode::DoublePulse, "DoublePulse"),
+            (PulseMode::DialRing, "DialRing"),
+            (
+                self.led_state.timed(PulseMode::Breathing { interval_ms: NonZeroU16::MIN }),
+                "Breathing",
+            ),
+            (
+                self.led_state
+                    .timed(PulseMode::Blink { on_ms: NonZeroU16::MIN, off_ms: NonZeroU16::MIN }),
+                "Blink",
+            ),
+            (self.led_state.timed(PulseMode::FadeTo { ms: 0 }), "FadeTo"),
+            (
+                self.led_state.timed(PulseMode::ColorCycle { period_ms: NonZeroU16::MIN }),
+                "ColorCycle",
+            ),
+        ];
         egui::ComboBox::from_label("Pulse Mode")
             .selected_text(format!("{:?}", self.led_state.pulse_mode))
             .show_ui(ui, |ui| {
-                ui.selectable_value(&mut self.led_state.pulse_mode, PulseMode::Solid, "Solid");
-                ui.selectable_value(
-                    &mut self.led_state.pulse_mode,
-                    PulseMode::DialTurn,
-                    "DialTurn",
-                );
-                ui.selectable_value(
-                    &mut self.led_state.pulse_mode,
-                    PulseMode::Breathing {
-                        interval_ms: NonZeroU16::new(self.led_state.if_breathing_interval_ms)
-                            .unwrap(),
-                    },
-                    "Breathing",
-                );
+                for &(mode, name) in &modes {
+                    ui.selectable_value(&mut self.led_state.pulse_mode, mode, name);
+                }
             });
 
-        // Duration for pulse mode if breathing
+        // Timing of the pulse modes that have one
         ui.scope(|ui| {
-            ui.set_visible(matches!(self.led_state.pulse_mode, PulseMode::Breathing { .. }));
+            ui.set_visible(!matches!(
+                self.led_state.pulse_mode,
+                PulseMode::Solid
+                    | PulseMode::DialTurn
+                    | PulseMode::DoublePulse
+                    | PulseMode::DialRing
+            ));
 
             let response = ui.add(
-                egui::Slider::new(&mut self.led_state.if_breathing_interval_ms, 1..=u16::MAX)
-                    .text("Breathing (half) interval (ms)")
+                egui::Slider::new(&mut self.led_state.interval_ms, 1..=u16::MAX)
+                    .text("Interval (ms)")
                     .clamp_to_range(true),
             );
             if response.changed() {
-                self.led_state.pulse_mode = PulseMode::Breathing {
-                    interval_ms: NonZeroU16::new(self.led_state.if_breathing_interval_ms).unwrap(),
-                };
+                self.led_state.pulse_mode = self.led_state.timed(self.led_state.pulse_mode);
             }
         });
     }
 
+    /// Add a control for each newly listed light and fan, starting at the bottom of its range.
+    /// Nothing is sent until the control is moved.
+    fn add_controls(&mut self) {
+        for light in &self.targets.lights[self.light_state.len()..] {
+            self.light_state.push(LightSetpoint {
+                brightness: *light.brightness.start(),
+                temperature: *light.temperature.start(),
+            });
+        }
+        for fan in &self.targets.fans[self.fan_speeds.len()..] {
+            self.fan_speeds.push(*fan.speed.start());
+        }
+    }
+
     fn lighting_configuration_section(&mut self, ui: &mut eframe::egui::Ui) {
-        ui.label("Front Lights");
-        ui.group(|ui| {
-            ui.add(
-                egui::Slider::new(&mut self.light_state[0].brightness, 0..=u16::MAX)
-                    .text("Brightness")
-                    .clamp_to_range(true),
-            );
-            ui.add(
-                egui::Slider::new(&mut self.light_state[0].temperature, 0..=u16::MAX)
-                    .text("Temperature")
-                    .clamp_to_range(true),
-            );
-        });
-        ui.label("Back Lights");
-        ui.group(|ui| {
-            ui.add(
-                egui::Slider::new(&mut self.light_state[1].brightness, 0..=u16::MAX)
-                    .text("Brightness")
-                    .clamp_to_range(true),
-            );
+        for (light, setpoint) in self.targets.lights.iter().zip(&mut self.light_state) {
+            ui.label(&light.name);
+            ui.group(|ui| {
+                ui.add(
+                    egui::Slider::new(&mut setpoint.brightness, light.brightness.clone())
+                        .text("Brightness")
+                        .clamp_to_range(true),
+                );
+                ui.add(
+                    egui::Slider::new(&mut setpoint.temperature, light.temperature.clone())
+                        .text("Temperature (K)")
+                        .clamp_to_range(true),
+                );
+            });
+        }
+    }
+
+    fn fan_configuration_section(&mut self, ui: &mut eframe::egui::Ui) {
+        for (fan, speed) in self.targets.fans.iter().zip(&mut self.fan_speeds) {
             ui.add(
-                egui::Slider::new(&mut self.light_state[1].temperature, 0..=u16::MAX)
-                    .text("Temperature")
-                    .clamp_to_range(true),
+                egui::Slider::new(speed, fan.speed.clone()).text(&fan.name).clamp_to_range(true),
             );
-        });
+            let status = match fan.target.address() {
+                Address::Single(index) => self.telemetry.fan(index),
+                _ => None,
+            };
+            match status {
+                Some(status) if status.stalled => {
+                    ui.colored_label(egui::Color32::RED, format!("{} has stalled", fan.name))
+                },
+                Some(status) => ui.label(format!("{}: {} RPM", fan.name, status.rpm.0)),
+                None => ui.label(format!("{}: speed unknown", fan.name)),
+            };
+        }
+        if ui.button("Refresh fan status").clicked() {
+            self.command_tx.send(Command::GetFanStatus { target: Target::ALL }).unwrap();
+        }
     }
 
     fn other_commands_section(&mut self, ui: &mut eframe::egui::Ui) {
@@ -194,8 +329,9 @@ impl epi::App for App {
             std::thread::sleep(Duration::from_millis(1));
         });
 
-        // Update the led on startup
+        // Update the led on startup, and find out which lights and fans to show.
         self.command_tx.send(self.led_state.into()).unwrap();
+        self.command_tx.send(Command::ListTargets).unwrap();
 
         // Setup some fonts
         let mut fonts = FontDefinitions::default();
@@ -207,10 +343,25 @@ impl epi::App for App {
     }
 
     fn update(&mut self, ctx: &egui::CtxRef, _frame: &mut epi::Frame<'_>) {
+        self.add_controls();
         let current_led_state = self.led_state;
-        let current_light_state = self.light_state;
+        let current_led_preview = self.led_preview(0, 1);
+        let current_light_state = self.light_state.clone();
+        let current_fan_speeds = self.fan_speeds.clone();
         egui::CentralPanel::default().show(ctx, |ui| {
             if let Ok(report) = self.report_rx.try_recv() {
+                // The panel's own list replaces the defaults once it's complete.
+                if report == Report::TargetListDone {
+                    self.targets = std::mem::take(&mut self.listed_targets);
+                    self.light_state.clear();
+                    self.fan_speeds.clear();
+                } else if let Report::DialValue { diff } = report {
+                    self.last_dial_turn = Some(Instant::now());
+                    self.dial_position += i32::from(diff);
+                } else {
+                    self.telemetry.add(&report, Instant::now());
+                    self.listed_targets.add(&report);
+                }
                 self.last_recv_reports.push_back(report);
                 while self.last_recv_reports.len() > SHOW_LAST_COMMAND_NUM {
                     self.last_recv_reports.pop_front();
@@ -231,6 +382,12 @@ impl epi::App for App {
                     ui.separator();
                     ui.collapsing("Lighting", |ui| self.lighting_configuration_section(ui));
 
+                    // Fans
+                    if !self.targets.fans.is_empty() {
+                        ui.separator();
+                        ui.collapsing("Fans", |ui| self.fan_configuration_section(ui));
+                    }
+
                     // Bootloader command
                     ui.separator();
                     ui.collapsing("Other Commands", |ui| self.other_commands_section(ui));
@@ -250,16 +407,36 @@ impl epi::App for App {
 
         if self.led_state != current_led_state {
             self.command_tx.send(self.led_state.into()).unwrap();
+            self.led_changed_at = Instant::now();
+            self.led_previous = current_led_preview;
+        }
+        if let Some(command) = self.shadow.set_led_config(self.led_config) {
+            self.command_tx.send(command).unwrap();
+        }
+        // Keep the preview animating.
+        if self.led_state.pulse_mode != PulseMode::Solid {
+            ctx.request_repaint();
         }
 
-        if self.light_state != current_light_state {
-            for (target, state) in self.light_state.iter().enumerate() {
-                let target = target as u8;
-                self.command_tx
-                    .send(Command::Brightness { target, value: state.brightness })
-                    .unwrap();
+        // Only lights whose sliders moved, so that new controls don't turn the lights down to the
+        // bottom of their range.
+        for (control, (light, setpoint)) in
+            self.targets.lights.iter().zip(&self.light_state).enumerate()
+        {
+            if current_light_state.get(control) == Some(setpoint) {
+                continue;
+            }
+            if let Address::Single(index) = light.target.address() {
+                if let Some(command) = self.shadow.set_light(index, *setpoint, 0) {
+                    self.command_tx.send(command).unwrap();
+                }
+            }
+        }
+
+        for (index, (fan, speed)) in self.targets.fans.iter().zip(&self.fan_speeds).enumerate() {
+            if current_fan_speeds.get(index) != Some(speed) {
                 self.command_tx
-                    .send(Command::Temperature { target, value: state.temperature })
+                    .send(Command::FanSpeed { target: fan.target, value: *speed })
                     .unwrap();
             }
         }
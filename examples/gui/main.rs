@@ -1,5 +1,6 @@
 use anyhow::Result;
 use eframe::run_native;
+use panel_protocol::queue::CommandQueue;
 use std::{
     env,
     sync::{
@@ -7,11 +8,15 @@ use std::{
         Arc,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 mod app;
 mod panel;
 
+// Dragging a slider produces new commands every frame, far more than a 115200 baud link can
+// carry. Pace them and only send the latest value for each light.
+const MIN_COMMAND_INTERVAL: Duration = Duration::from_millis(5);
+
 fn print_usage(args: &[String]) {
     println!("Usage: {} <tty_port>", args[0]);
     println!();
@@ -34,6 +39,7 @@ fn main() -> Result<()> {
     let should_exit = Arc::new(AtomicBool::new(false));
     thread::spawn({
         let mut panel = panel::Panel::new(port)?;
+        let mut command_queue = CommandQueue::new(MIN_COMMAND_INTERVAL);
 
         move || loop {
             match panel.poll() {
@@ -50,7 +56,10 @@ fn main() -> Result<()> {
                 },
             }
 
-            while let Ok(command) = command_rx.try_recv() {
+            for command in command_rx.try_iter() {
+                command_queue.push(command);
+            }
+            if let Some(command) = command_queue.pop(Instant::now()) {
                 panel.send(&command).unwrap();
             }
             thread::sleep(Duration::from_micros(50));
@@ -11,7 +11,8 @@ use std::{
 
 const REPORT_QUEUE_SIZE: usize = 6;
 
-static TTY_TIMEOUT: Duration = Duration::from_millis(500);
+// Keep this short, queued commands are only sent between reads.
+static TTY_TIMEOUT: Duration = Duration::from_millis(1);
 
 pub struct Panel {
     tty: TTYPort,
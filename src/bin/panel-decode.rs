@@ -0,0 +1,130 @@
+/// Decodes a stream of protocol bytes, e.g. from a logic analyzer trace of the UART, into
+/// Commands or Reports.
+use panel_protocol::{
+    capture::{Direction, Message},
+    hex_dump,
+};
+use std::{
+    env,
+    fs::File,
+    io::{self, Read},
+    process,
+};
+
+enum Format {
+    Hex,
+    Raw,
+}
+
+fn print_usage(program: &str) {
+    println!("Usage: {program} <commands|reports> [--hex|--raw] [file]");
+    println!();
+    println!("Decodes the bytes in file (or stdin) as a stream of Commands sent by the host, or ");
+    println!("Reports sent by the panel, and prints each message with its byte offset. Bytes ");
+    println!("that don't decode are flagged as malformed.");
+    println!();
+    println!("The input is a hex dump by default, either plain hex digits or the output of ");
+    println!("xxd or hexdump -C. Use --raw to decode a binary file.");
+}
+
+fn print_line(offset: usize, bytes: &[u8], description: &str) {
+    let hex = bytes.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<_>>().join(" ");
+    println!("{offset:#06x}  {hex:<24}  {description}");
+}
+
+/// Print every message in `bytes`, returning the number of bytes that didn't decode.
+fn decode(direction: Direction, bytes: &[u8]) -> usize {
+    let mut offset = 0;
+    let mut malformed_start = None;
+    let mut malformed_len = 0;
+
+    while offset < bytes.len() {
+        let parsed = Message::try_from(direction, &bytes[offset..]);
+        if parsed.is_err() {
+            // Keep going one byte at a time until the stream resynchronizes.
+            malformed_start.get_or_insert(offset);
+            malformed_len += 1;
+            offset += 1;
+            continue;
+        }
+
+        if let Some(start) = malformed_start.take() {
+            print_line(
+                start,
+                &bytes[start..offset],
+                &format!("MALFORMED ({} bytes)", offset - start),
+            );
+        }
+        match parsed {
+            Ok(Some((message, len))) => {
+                print_line(offset, &bytes[offset..offset + len], &message.to_string());
+                offset += len;
+            },
+            _ => {
+                let len = bytes.len() - offset;
+                print_line(offset, &bytes[offset..], &format!("INCOMPLETE ({len} bytes)"));
+                malformed_len += len;
+                offset += len;
+            },
+        }
+    }
+
+    if let Some(start) = malformed_start {
+        print_line(start, &bytes[start..], &format!("MALFORMED ({} bytes)", bytes.len() - start));
+    }
+
+    malformed_len
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let direction = match args.get(1).map(String::as_str) {
+        Some("commands") => Direction::ToPanel,
+        Some("reports") => Direction::ToHost,
+        _ => {
+            print_usage(&args[0]);
+            process::exit(2);
+        },
+    };
+
+    let mut format = Format::Hex;
+    let mut path = None;
+    for arg in &args[2..] {
+        match arg.as_str() {
+            "--hex" => format = Format::Hex,
+            "--raw" => format = Format::Raw,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => {
+                print_usage(&args[0]);
+                process::exit(2);
+            },
+        }
+    }
+
+    let mut input = Vec::new();
+    let result = match path {
+        Some(path) => File::open(path).and_then(|mut file| file.read_to_end(&mut input)),
+        None => io::stdin().read_to_end(&mut input),
+    };
+    if let Err(e) = result {
+        println!("Failed to read the input: {e}");
+        process::exit(1);
+    }
+
+    let bytes = match format {
+        Format::Raw => input,
+        Format::Hex => match hex_dump::parse(&String::from_utf8_lossy(&input)) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                println!("Failed to parse the hex dump, {e}");
+                process::exit(1);
+            },
+        },
+    };
+
+    let malformed_len = decode(direction, &bytes);
+    if malformed_len > 0 {
+        println!("{malformed_len} of {} bytes did not decode", bytes.len());
+        process::exit(1);
+    }
+}
@@ -0,0 +1,166 @@
+/// Replays a capture recorded with `panel_protocol::capture::Recorder`, either as a fake panel
+/// sending the recorded reports to a host application, or as a fake host sending the recorded
+/// commands to a real or simulated panel.
+use panel_protocol::{
+    capture::{self, Direction, Record},
+    tty::{self, Pty},
+    CommandReader, MAX_SERIAL_MESSAGE_LEN,
+};
+use std::{
+    env,
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    path::Path,
+    process, thread,
+    time::{Duration, Instant},
+};
+
+const COMMAND_QUEUE_SIZE: usize = 16;
+
+static TTY_TIMEOUT: Duration = Duration::from_millis(500);
+
+struct Options {
+    speed: f64,
+    step: bool,
+}
+
+fn print_usage(program: &str) {
+    println!("Usage: {program} reports <capture_file> [--speed <multiplier>] [--step]");
+    println!("       {program} commands <capture_file> <tty_port> [--speed <multiplier>] [--step]");
+    println!();
+    println!("reports:  Create a pseudo-terminal that behaves like the recorded panel. Once a ");
+    println!("          host application is connected to it, press enter to play back the ");
+    println!("          recorded Reports.");
+    println!("commands: Send the recorded Commands to the panel on tty_port.");
+    println!();
+    println!("Messages are sent with their original timing, sped up or slowed down by --speed. ");
+    println!("With --step, each message is sent after pressing enter instead.");
+}
+
+fn wait_for_enter() -> io::Result<()> {
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(())
+}
+
+/// Send the records travelling in `direction` to `output`, following the options.
+fn replay(
+    records: &[Record],
+    direction: Direction,
+    output: &mut impl Write,
+    options: &Options,
+) -> io::Result<()> {
+    let records: Vec<_> = records.iter().filter(|record| record.direction == direction).collect();
+    let Some(first) = records.first() else {
+        println!("The capture contains nothing to replay.");
+        return Ok(());
+    };
+
+    let start = Instant::now();
+    for (index, record) in records.iter().enumerate() {
+        if options.step {
+            print!("[{}/{}] Press enter to send the next message", index + 1, records.len());
+            io::stdout().flush()?;
+            wait_for_enter()?;
+        } else {
+            let offset = (record.timestamp - first.timestamp).div_f64(options.speed);
+            thread::sleep((start + offset).saturating_duration_since(Instant::now()));
+        }
+
+        output.write_all(&record.bytes)?;
+        match record.message {
+            Some(message) => println!("Sent: {message}"),
+            None => println!("Sent malformed bytes: {:02x?}", record.bytes),
+        }
+    }
+
+    Ok(())
+}
+
+fn replay_reports(records: &[Record], options: &Options) -> io::Result<()> {
+    let pty = Pty::open()?;
+    println!("Replaying panel listening on {}", pty.path().display());
+
+    // Show what the host application does in response to the replayed reports.
+    thread::spawn({
+        let mut master = pty.master().try_clone()?;
+        move || {
+            let mut protocol = CommandReader::new();
+            let mut read_buf = [0u8; MAX_SERIAL_MESSAGE_LEN];
+            while let Ok(count) = master.read(&mut read_buf) {
+                match protocol.process_bytes::<COMMAND_QUEUE_SIZE>(&read_buf[..count]) {
+                    Ok(commands) => {
+                        for command in commands {
+                            println!("Received command: {command:?}");
+                        }
+                    },
+                    Err(e) => {
+                        println!("Failed to process bytes, discarding buffered input: {e:?}");
+                        protocol = CommandReader::new();
+                    },
+                }
+            }
+        }
+    });
+
+    println!("Press enter to start the replay");
+    wait_for_enter()?;
+    replay(records, Direction::ToHost, &mut pty.master().try_clone()?, options)?;
+
+    println!("Replay finished, press enter to exit");
+    wait_for_enter()
+}
+
+fn replay_commands(records: &[Record], tty_port: &str, options: &Options) -> io::Result<()> {
+    let mut tty = tty::open(Path::new(tty_port), TTY_TIMEOUT)?;
+    replay(records, Direction::ToPanel, &mut tty, options)
+}
+
+fn parse_options(args: &[String]) -> Option<Options> {
+    let mut options = Options { speed: 1.0, step: false };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--speed" => {
+                options.speed = args.next()?.parse().ok().filter(|speed: &f64| *speed > 0.0)?
+            },
+            "--step" => options.step = true,
+            _ => return None,
+        }
+    }
+    Some(options)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (mode, tty_port, options) = match args.get(1).map(String::as_str) {
+        Some("reports") if args.len() >= 3 => ("reports", None, parse_options(&args[3..])),
+        Some("commands") if args.len() >= 4 => {
+            ("commands", Some(&args[3]), parse_options(&args[4..]))
+        },
+        _ => ("", None, None),
+    };
+    let Some(options) = options else {
+        print_usage(&args[0]);
+        process::exit(2);
+    };
+
+    let capture_path = &args[2];
+    let records =
+        match File::open(capture_path).and_then(|file| capture::read(BufReader::new(file))) {
+            Ok(records) => records,
+            Err(e) => {
+                println!("Failed to read capture {capture_path}: {e}");
+                process::exit(1);
+            },
+        };
+
+    let result = match tty_port {
+        Some(tty_port) => replay_commands(&records, tty_port, &options),
+        None => replay_reports(&records, &options),
+    };
+    if let Err(e) = result {
+        println!("Failed to replay the {mode}: {e}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,672 @@
+/// A virtual panel that speaks the device side of the protocol over a pseudo-terminal, so the
+/// examples and host applications can be run without hardware.
+use panel_protocol::{
+    color::{LedConfig, Rgb},
+    config::{Config, Watchdog},
+    fan::{CurvePoints, StallDetector},
+    sequence::{Sequence, SequenceUpload},
+    tty::Pty,
+    units::{Duty, Kelvin, Rpm},
+    Command, CommandReader, PulseMode, Report, Subsystem, Target, TargetKind, TargetName,
+    MAX_SERIAL_MESSAGE_LEN,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fmt,
+    fs::File,
+    io::{self, Read, Write},
+    os::unix::io::AsRawFd,
+    process,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+const COMMAND_QUEUE_SIZE: usize = 16;
+
+/// How often the simulated panel checks on the host and its fans.
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+const KEY_CTRL_C: u8 = 0x03;
+const KEY_ESCAPE: u8 = 0x1b;
+
+/// The lights and fans the simulated panel starts out with and lists to hosts.
+const LIGHTS: [(u8, &str); 2] = [(0, "Front Lights"), (1, "Back Lights")];
+const FANS: [(u8, &str); 1] = [(0, "Fan")];
+const RING: (&str, usize) = ("Dial Ring", 24);
+const SENSORS: [(u8, &str); 1] = [(0, "Board")];
+/// The speed of every simulated fan at full duty.
+const FAN_MAX_RPM: u16 = 1800;
+/// The simulated board temperature with the lights off and the fans stopped, and how much the
+/// lights at full brightness heat it up and the fans at full speed cool it down.
+const IDLE_CENTI_CELSIUS: i32 = 2500;
+const LIGHTS_CENTI_CELSIUS: i32 = 2000;
+const FANS_CENTI_CELSIUS: i32 = -1000;
+/// How much the `h` and `c` keys change the temperature around the panel.
+const AMBIENT_STEP_CENTI_CELSIUS: i32 = 500;
+
+const BRIGHTNESS_RANGE: (u16, u16) = (0, 4095);
+const TEMPERATURE_RANGE: (u16, u16) = (Kelvin::WARM_WHITE.get(), Kelvin::DAYLIGHT.get());
+const FAN_SPEED_RANGE: (u16, u16) = (Duty::OFF.0, Duty::FULL.0);
+
+#[derive(Clone, Default)]
+struct LightState {
+    brightness: u16,
+    temperature: u16,
+}
+
+/// What a scene stores.
+#[derive(Clone)]
+struct Scene {
+    lights: BTreeMap<u8, LightState>,
+    fans: BTreeMap<u8, u16>,
+    led: (u8, u8, u8, PulseMode),
+}
+
+struct PanelState {
+    lights: BTreeMap<u8, LightState>,
+    fans: BTreeMap<u8, u16>,
+    led: (u8, u8, u8, PulseMode),
+    scenes: BTreeMap<u8, Scene>,
+    led_config: LedConfig,
+    sequences: BTreeMap<u8, Sequence>,
+    upload: Option<SequenceUpload>,
+    /// The slot of the sequence playing on the LED. Simulated sequences never finish.
+    playing: Option<u8>,
+    ring: Vec<Rgb>,
+    next_frame: Vec<Rgb>,
+    config: Config,
+    /// The settings kept across restarts, which the simulated panel never does.
+    saved_config: Config,
+    watchdog: Watchdog,
+    started: Instant,
+    /// Fans jammed with the `j` key, which don't turn whatever their duty cycle.
+    jammed_fans: BTreeSet<u8>,
+    stall_detectors: BTreeMap<u8, StallDetector>,
+    stalled_fans: BTreeSet<u8>,
+    last_fan_status_ms: u32,
+    /// The sensor and curve regulating each fan under automatic control.
+    fan_curves: BTreeMap<u8, (u8, CurvePoints)>,
+    /// How much warmer than usual it is around the panel, changed with the `h` and `c` keys.
+    ambient_centi_celsius: i32,
+    last_thermal_ms: u32,
+}
+
+impl Default for PanelState {
+    fn default() -> Self {
+        Self {
+            lights: LIGHTS.iter().map(|&(index, _)| (index, LightState::default())).collect(),
+            fans: FANS.iter().map(|&(index, _)| (index, 0)).collect(),
+            led: (0, 0, 0, PulseMode::Solid),
+            led_config: LedConfig::default(),
+            scenes: BTreeMap::new(),
+            sequences: BTreeMap::new(),
+            upload: None,
+            playing: None,
+            ring: vec![Rgb::BLACK; RING.1],
+            next_frame: vec![Rgb::BLACK; RING.1],
+            config: Config::FACTORY,
+            saved_config: Config::FACTORY,
+            watchdog: Watchdog::new(0),
+            started: Instant::now(),
+            jammed_fans: BTreeSet::new(),
+            stall_detectors: BTreeMap::new(),
+            stalled_fans: BTreeSet::new(),
+            last_fan_status_ms: 0,
+            fan_curves: BTreeMap::new(),
+            ambient_centi_celsius: 0,
+            last_thermal_ms: 0,
+        }
+    }
+}
+
+/// Apply `f` to every light or fan `target` addresses. Lights and fans that weren't listed are
+/// created when they're first addressed individually, and from then on `Target::ALL` reaches
+/// them too.
+fn for_each_target<T: Default>(
+    map: &mut BTreeMap<u8, T>,
+    target: Target,
+    mut f: impl FnMut(&mut T),
+) {
+    if target != Target::ALL {
+        for index in (0..=Target::MAX_INDEX).filter(|&index| target.contains(index)) {
+            map.entry(index).or_default();
+        }
+    }
+    for (_, value) in map.iter_mut().filter(|(&index, _)| target.contains(index)) {
+        f(value);
+    }
+}
+
+impl PanelState {
+    /// Milliseconds since the panel started, like a firmware tick counter.
+    fn now_ms(&self) -> u32 {
+        self.started.elapsed().as_millis() as u32
+    }
+
+    /// Apply a command, returning the reports the panel sends in response.
+    fn apply(&mut self, command: Command) -> Vec<Report> {
+        match command {
+            Command::Brightness { target, value } => {
+                for_each_target(&mut self.lights, target, |light| light.brightness = value)
+            },
+            Command::Temperature { target, value } => {
+                for_each_target(&mut self.lights, target, |light| light.temperature = value)
+            },
+            // The simulated lights have no inertia, so fades finish immediately.
+            Command::BrightnessFade { target, value, .. } => {
+                for_each_target(&mut self.lights, target, |light| light.brightness = value)
+            },
+            Command::TemperatureFade { target, value, .. } => {
+                for_each_target(&mut self.lights, target, |light| light.temperature = value)
+            },
+            Command::Light { target, brightness, temperature, .. } => {
+                for_each_target(&mut self.lights, target, |light| {
+                    *light = LightState { brightness, temperature }
+                })
+            },
+            Command::Led { r, g, b, pulse_mode } => {
+                self.led = (r, g, b, pulse_mode);
+                self.playing = None;
+            },
+            Command::FanSpeed { target, value } => {
+                for_each_target(&mut self.fans, target, |speed| *speed = value);
+                self.fan_curves.retain(|&index, _| !target.contains(index));
+            },
+            Command::FanCurve { target, sensor, points } => {
+                for_each_target(&mut self.fans, target, |_| {});
+                for &index in self.fans.keys().filter(|&&index| target.contains(index)) {
+                    if points.is_empty() {
+                        self.fan_curves.remove(&index);
+                    } else {
+                        self.fan_curves.insert(index, (sensor, points));
+                    }
+                }
+            },
+            Command::GetThermal => return self.thermal(),
+            Command::Bootload => println!("(A real panel would now restart in bootloader mode.)"),
+            Command::SelfTest => return self.self_test(),
+            Command::ListTargets => return list_targets(),
+            Command::SequenceBegin { slot, keyframe_count, loop_count } => {
+                self.upload = SequenceUpload::begin(slot, keyframe_count, loop_count);
+                return self.finish_upload();
+            },
+            Command::SequenceKeyframe { slot, index, keyframe } => {
+                if let Some(upload) = &mut self.upload {
+                    upload.add(slot, index, keyframe);
+                }
+                return self.finish_upload();
+            },
+            Command::Pixels { start, count, color } => {
+                for pixel in self.ring.iter_mut().skip(start.into()).take(count.into()) {
+                    *pixel = color;
+                }
+            },
+            Command::FramePixels { start, colors } => {
+                for (pixel, color) in self.next_frame.iter_mut().skip(start.into()).zip(colors) {
+                    *pixel = color;
+                }
+            },
+            Command::ShowFrame => self.ring.clone_from(&self.next_frame),
+            Command::LedConfig { global_brightness, gamma } => {
+                self.led_config = LedConfig { global_brightness, gamma }
+            },
+            Command::SaveScene { id } => {
+                let scene =
+                    Scene { lights: self.lights.clone(), fans: self.fans.clone(), led: self.led };
+                self.scenes.insert(id, scene);
+                return vec![Report::SceneSaved { id }];
+            },
+            // Like fades, recalling a scene finishes immediately.
+            Command::RecallScene { id, .. } => match self.scenes.get(&id).cloned() {
+                Some(Scene { lights, fans, led }) => {
+                    self.lights = lights;
+                    self.fans = fans;
+                    self.led = led;
+                    self.playing = None;
+                },
+                None => println!("(No scene is stored as {id}.)"),
+            },
+            Command::SetConfig { key, value } => {
+                if !self.config.set(key, value) {
+                    println!("({value} is out of range for {}.)", key.name());
+                }
+                return vec![Report::ConfigValue { key, value: self.config.get(key) }];
+            },
+            Command::GetConfig { key } => {
+                return vec![Report::ConfigValue { key, value: self.config.get(key) }]
+            },
+            Command::SaveConfig => self.saved_config = self.config,
+            Command::FactoryReset => {
+                self.config = Config::FACTORY;
+                self.saved_config = Config::FACTORY;
+            },
+            Command::OfflineLed { r, g, b, pulse_mode } => {
+                self.config.offline_led_color = Rgb::new(r, g, b);
+                self.config.offline_pulse_mode = pulse_mode;
+            },
+            Command::Heartbeat => {},
+            Command::GetFanStatus { target } => return self.fan_status(target),
+            Command::PlaySequence { slot } => {
+                if self.sequences.contains_key(&slot) {
+                    self.playing = Some(slot);
+                } else {
+                    println!("(No sequence is stored in slot {slot}.)");
+                }
+            },
+        }
+        Vec::new()
+    }
+
+    /// The speed of a fan, which is proportional to its duty cycle unless it's jammed.
+    fn fan_rpm(&self, index: u8, duty: u16) -> Rpm {
+        if self.jammed_fans.contains(&index) {
+            return Rpm(0);
+        }
+        Rpm((u32::from(duty) * u32::from(FAN_MAX_RPM) / u32::from(u16::MAX)) as u16)
+    }
+
+    /// Update which fans have stalled, as the firmware does continuously.
+    fn check_fans(&mut self, now_ms: u32) {
+        let fans: Vec<_> = self.fans.iter().map(|(&index, &duty)| (index, duty)).collect();
+        for (index, duty) in fans {
+            let rpm = self.fan_rpm(index, duty);
+            if self.stall_detectors.entry(index).or_default().update(Duty(duty), rpm, now_ms) {
+                self.stalled_fans.insert(index);
+            } else {
+                self.stalled_fans.remove(&index);
+            }
+        }
+    }
+
+    fn fan_status(&self, target: Target) -> Vec<Report> {
+        self.fans
+            .iter()
+            .filter(|(&index, _)| target.contains(index))
+            .map(|(&index, &duty)| Report::FanStatus {
+                target: Target::single(index).unwrap(),
+                rpm: self.fan_rpm(index, duty).0,
+                duty,
+                stalled: self.stalled_fans.contains(&index),
+            })
+            .collect()
+    }
+
+    /// The temperature of a sensor in hundredths of a degree, which rises with the brightness of
+    /// the lights and falls with the speed of the fans.
+    fn centi_celsius(&self, _sensor: u8) -> i16 {
+        let average = |values: Vec<u16>, max: u16| {
+            let count = values.len().max(1) as i32;
+            values.iter().map(|&value| i32::from(value)).sum::<i32>() / count * 100 / i32::from(max)
+        };
+        let lights = average(
+            self.lights.values().map(|light| light.brightness.min(BRIGHTNESS_RANGE.1)).collect(),
+            BRIGHTNESS_RANGE.1,
+        );
+        let fans = average(self.fans.values().copied().collect(), Duty::FULL.0);
+        let centi_celsius = IDLE_CENTI_CELSIUS
+            + LIGHTS_CENTI_CELSIUS * lights / 100
+            + FANS_CENTI_CELSIUS * fans / 100
+            + self.ambient_centi_celsius;
+        centi_celsius.clamp(i16::MIN.into(), i16::MAX.into()) as i16
+    }
+
+    fn thermal(&self) -> Vec<Report> {
+        SENSORS
+            .iter()
+            .map(|&(sensor, _)| Report::Thermal {
+                sensor,
+                centi_celsius: self.centi_celsius(sensor),
+            })
+            .collect()
+    }
+
+    /// Set the fans under automatic control from their curves, and keep every fan at the
+    /// offline speed or faster while the host is gone, as the firmware does continuously.
+    fn regulate_fans(&mut self) {
+        let duties: Vec<_> = self
+            .fan_curves
+            .iter()
+            .filter_map(|(&index, (sensor, points))| {
+                Some((index, points.duty_at(self.centi_celsius(*sensor))?))
+            })
+            .collect();
+        for (index, duty) in duties {
+            self.fans.insert(index, duty.0);
+        }
+        if self.watchdog.is_offline() {
+            for duty in self.fans.values_mut() {
+                *duty = self.config.offline_fan_duty(Duty(*duty)).0;
+            }
+        }
+    }
+
+    /// Store the sequence being uploaded once it's complete.
+    fn finish_upload(&mut self) -> Vec<Report> {
+        match self.upload.take_if(|upload| upload.is_complete()) {
+            Some(upload) => {
+                let slot = upload.slot();
+                let sequence = upload.finish().unwrap();
+                let keyframe_count = sequence.keyframes().len() as u8;
+                self.sequences.insert(slot, sequence);
+                vec![Report::SequenceStored { slot, keyframe_count }]
+            },
+            None => Vec::new(),
+        }
+    }
+
+    /// Every simulated subsystem passes.
+    fn self_test(&self) -> Vec<Report> {
+        let subsystems = [Subsystem::LedRed, Subsystem::LedGreen, Subsystem::LedBlue]
+            .iter()
+            .copied()
+            .chain(self.lights.keys().map(|&target| Subsystem::Light(target)))
+            .chain(self.fans.keys().map(|&target| Subsystem::Fan(target)))
+            .chain([Subsystem::Dial, Subsystem::Button]);
+
+        subsystems
+            .map(|subsystem| Report::SelfTestResult { subsystem, passed: true })
+            .chain([Report::SelfTestDone])
+            .collect()
+    }
+}
+
+fn list_targets() -> Vec<Report> {
+    let info = |index, kind, name, (min, max)| Report::TargetInfo {
+        target: Target::single(index).unwrap(),
+        kind,
+        name: TargetName::new(name).unwrap(),
+        min,
+        max,
+    };
+
+    let lights = LIGHTS.iter().flat_map(|&(index, name)| {
+        [
+            info(index, TargetKind::Brightness, name, BRIGHTNESS_RANGE),
+            info(index, TargetKind::Temperature, name, TEMPERATURE_RANGE),
+        ]
+    });
+    let fans =
+        FANS.iter().map(|&(index, name)| info(index, TargetKind::FanSpeed, name, FAN_SPEED_RANGE));
+
+    let (ring_name, pixel_count) = RING;
+    let ring = info(0, TargetKind::LedRing, ring_name, (0, pixel_count as u16 - 1));
+
+    lights.chain(fans).chain([ring, Report::TargetListDone]).collect()
+}
+
+impl fmt::Display for PanelState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (r, g, b, pulse_mode) = self.led;
+        write!(f, "LED: #{r:02x}{g:02x}{b:02x} {pulse_mode:?}")?;
+        if let Some(slot) = self.playing {
+            write!(f, " playing sequence {slot}")?;
+        }
+        let LedConfig { global_brightness, gamma } = self.led_config;
+        write!(f, " (brightness {global_brightness}, gamma {}.{})", gamma / 10, gamma % 10)?;
+        for (target, light) in &self.lights {
+            write!(
+                f,
+                " | Light {target}: brightness {} temperature {}K",
+                light.brightness, light.temperature
+            )?;
+        }
+        for (&target, &speed) in &self.fans {
+            let rpm = self.fan_rpm(target, speed).0;
+            write!(f, " | Fan {target}: {}% {rpm} RPM", Duty(speed).percent())?;
+            if self.stalled_fans.contains(&target) {
+                write!(f, " stalled")?;
+            }
+            if let Some((sensor, _)) = self.fan_curves.get(&target) {
+                write!(f, " (following sensor {sensor})")?;
+            }
+        }
+        for &(sensor, name) in &SENSORS {
+            let centi_celsius = self.centi_celsius(sensor);
+            write!(f, " | {name}: {}.{:02} °C", centi_celsius / 100, (centi_celsius % 100).abs())?;
+        }
+        if self.watchdog.is_offline() {
+            write!(f, " | Offline")?;
+        }
+        let lit = self.ring.iter().filter(|&&pixel| pixel != Rgb::BLACK).count();
+        write!(f, " | Ring: {lit}/{} pixels lit", self.ring.len())?;
+        write!(f, " | Config: {:?}", self.config)?;
+        if self.config != self.saved_config {
+            write!(f, " (not saved)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Switches stdin to unbuffered, unechoed input for as long as it's alive.
+struct RawStdin {
+    original: libc::termios,
+}
+
+impl RawStdin {
+    fn enable() -> io::Result<Self> {
+        let fd = io::stdin().as_raw_fd();
+        unsafe {
+            let mut original = std::mem::zeroed();
+            if libc::tcgetattr(fd, &mut original) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut raw = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(fd, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(Self { original })
+        }
+    }
+}
+
+impl Drop for RawStdin {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(io::stdin().as_raw_fd(), libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+fn print_usage(args: &[String]) {
+    println!("Usage: {}", args[0]);
+    println!();
+    println!("The program creates a pseudo-terminal that behaves like a panel. Point a host ");
+    println!("application at the printed TTY path, and every Command it sends is applied to ");
+    println!("the simulated panel state.");
+    println!();
+    print_keys();
+}
+
+fn print_keys() {
+    println!("Keys:");
+    println!("  right, +    turn the dial clockwise");
+    println!("  left, -     turn the dial counter-clockwise");
+    println!("  shift+right, shift+left, ], [");
+    println!("              turn the dial by 10 steps");
+    println!("  space       press and release the button");
+    println!("  p / r       press / release the button");
+    println!("  j           jam or free fan 0, to try out stall detection");
+    println!("  h / c       make it 5 °C hotter / colder around the panel");
+    println!("  s           print the panel state");
+    println!("  q, ctrl-c   quit");
+}
+
+fn send_report(master: &mut File, report: &Report) -> io::Result<()> {
+    println!("Sent report: {report:?}");
+    master.write_all(&report.as_arrayvec())
+}
+
+fn receive_commands(mut master: File, state: Arc<Mutex<PanelState>>) {
+    let mut protocol = CommandReader::new();
+    let mut read_buf = [0u8; MAX_SERIAL_MESSAGE_LEN];
+
+    loop {
+        let count = match master.read(&mut read_buf) {
+            // Nothing to read, e.g. after the host closed its end, until the next host connects.
+            Ok(0) => {
+                thread::sleep(TICK_INTERVAL);
+                continue;
+            },
+            Ok(count) => count,
+            Err(e) => {
+                println!("Failed to read from the pseudo-terminal: {e}");
+                process::exit(1);
+            },
+        };
+
+        match protocol.process_bytes::<COMMAND_QUEUE_SIZE>(&read_buf[..count]) {
+            Ok(commands) => {
+                let mut state = state.lock().unwrap();
+                for command in commands {
+                    println!("Received command: {command:?}");
+                    let now_ms = state.now_ms();
+                    state.watchdog.feed(now_ms);
+                    for report in state.apply(command) {
+                        if let Err(e) = send_report(&mut master, &report) {
+                            println!("Failed to write to the pseudo-terminal: {e}");
+                            process::exit(1);
+                        }
+                    }
+                }
+                println!("{state}");
+            },
+            Err(e) => {
+                println!("Failed to process bytes, discarding buffered input: {e:?}");
+                protocol = CommandReader::new();
+            },
+        }
+    }
+}
+
+/// Do what the firmware does on its own: regulate and watch the fans, send their status every
+/// `fan_status_interval_ms` and the temperatures every `thermal_interval_ms`, and switch to the
+/// offline state whenever the host goes quiet for too long.
+fn run_timers(mut master: File, state: Arc<Mutex<PanelState>>) {
+    loop {
+        thread::sleep(TICK_INTERVAL);
+
+        let mut state = state.lock().unwrap();
+        let (config, now_ms) = (state.config, state.now_ms());
+        state.regulate_fans();
+        state.check_fans(now_ms);
+
+        let mut reports = Vec::new();
+        let interval_ms = u32::from(config.fan_status_interval_ms);
+        if interval_ms != 0 && now_ms.wrapping_sub(state.last_fan_status_ms) >= interval_ms {
+            state.last_fan_status_ms = now_ms;
+            reports.extend(state.fan_status(Target::ALL));
+        }
+        let interval_ms = u32::from(config.thermal_interval_ms);
+        if interval_ms != 0 && now_ms.wrapping_sub(state.last_thermal_ms) >= interval_ms {
+            state.last_thermal_ms = now_ms;
+            reports.extend(state.thermal());
+        }
+        for report in reports {
+            if let Err(e) = send_report(&mut master, &report) {
+                println!("Failed to write to the pseudo-terminal: {e}");
+                process::exit(1);
+            }
+        }
+
+        if state.watchdog.check(&config, now_ms) {
+            println!("(The host went quiet, switching to the offline state.)");
+            for command in config.offline_commands() {
+                state.apply(command);
+            }
+            state.regulate_fans();
+            println!("{state}");
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 1 {
+        print_usage(&args);
+        return Ok(());
+    }
+
+    let pty = Pty::open()?;
+    println!("Simulated panel listening on {}", pty.path().display());
+    print_keys();
+
+    let mut panel = PanelState::default();
+    for command in panel.config.power_on_commands() {
+        panel.apply(command);
+    }
+    let state = Arc::new(Mutex::new(panel));
+    thread::spawn({
+        let master = pty.master().try_clone()?;
+        let state = state.clone();
+        move || receive_commands(master, state)
+    });
+    thread::spawn({
+        let master = pty.master().try_clone()?;
+        let state = state.clone();
+        move || run_timers(master, state)
+    });
+
+    let mut master = pty.master().try_clone()?;
+    let mut send = |report: Report| send_report(&mut master, &report);
+    // Each step is a detent of the dial, reported as `dial_sensitivity` steps.
+    let turn = |steps: i16| {
+        let sensitivity = i16::from(state.lock().unwrap().config.dial_sensitivity);
+        Report::DialValue { diff: (steps * sensitivity).clamp(-128, 127) as i8 }
+    };
+
+    // Keys can also be piped in, e.g. to script a sequence of dial turns.
+    let is_terminal = unsafe { libc::isatty(io::stdin().as_raw_fd()) } == 1;
+    let _raw_stdin = if is_terminal { Some(RawStdin::enable()?) } else { None };
+    let mut keys = io::stdin().lock().bytes();
+    while let Some(key) = keys.next() {
+        match key? {
+            b'+' | b'=' => send(turn(1))?,
+            b'-' | b'_' => send(turn(-1))?,
+            b']' => send(turn(10))?,
+            b'[' => send(turn(-10))?,
+            b' ' => {
+                send(Report::Press)?;
+                send(Report::Release)?;
+            },
+            b'p' => send(Report::Press)?,
+            b'r' => send(Report::Release)?,
+            b'j' => {
+                let mut state = state.lock().unwrap();
+                if !state.jammed_fans.remove(&0) {
+                    state.jammed_fans.insert(0);
+                }
+            },
+            b'h' => state.lock().unwrap().ambient_centi_celsius += AMBIENT_STEP_CENTI_CELSIUS,
+            b'c' => state.lock().unwrap().ambient_centi_celsius -= AMBIENT_STEP_CENTI_CELSIUS,
+            b's' => println!("{}", state.lock().unwrap()),
+            b'q' | KEY_CTRL_C => break,
+            // Arrow keys arrive as "ESC [ C", shift+arrow as "ESC [ 1 ; 2 C".
+            KEY_ESCAPE => {
+                let mut sequence = Vec::new();
+                for key in keys.by_ref() {
+                    let key = key?;
+                    sequence.push(key);
+                    if key.is_ascii_alphabetic() || key == b'~' {
+                        break;
+                    }
+                }
+                let step =
+                    if sequence.ends_with(b";2C") || sequence.ends_with(b";2D") { 10 } else { 1 };
+                match sequence.last() {
+                    Some(b'C') => send(turn(step))?,
+                    Some(b'D') => send(turn(-step))?,
+                    _ => {},
+                }
+            },
+            _ => {},
+        }
+    }
+
+    Ok(())
+}
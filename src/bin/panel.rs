@@ -0,0 +1,428 @@
+/// A command line tool to control and monitor a panel.
+use panel_protocol::{
+    capture::{Message, Recorder},
+    discovery,
+    script::{Script, ScriptError},
+    self_test::SelfTest,
+    text, tty, ArrayVec, Command, Report, ReportReader, MAX_COMMAND_LEN, MAX_REPORT_LEN,
+    MAX_SERIAL_MESSAGE_LEN,
+};
+use serde_json::json;
+use serial_unix::TTYPort;
+use std::{
+    env,
+    fs::File,
+    io::{self, BufRead, Read, Write},
+    path::Path,
+    process,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+const REPORT_QUEUE_SIZE: usize = 16;
+
+static TTY_TIMEOUT: Duration = Duration::from_millis(100);
+static LIST_TARGETS_TIMEOUT: Duration = Duration::from_secs(1);
+static REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+const EXIT_PROTOCOL_ERROR: i32 = 1;
+const EXIT_USAGE_ERROR: i32 = 2;
+const EXIT_IO_ERROR: i32 = 3;
+const EXIT_SCRIPT_FAILED: i32 = 4;
+const EXIT_SELF_TEST_FAILED: i32 = 5;
+
+enum Failure {
+    Usage(String),
+    Io(io::Error),
+    Protocol(String),
+    ScriptFailed(String),
+    SelfTestFailed,
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Self {
+        Failure::Io(e)
+    }
+}
+
+impl From<text::ParseError> for Failure {
+    fn from(e: text::ParseError) -> Self {
+        Failure::Usage(e.to_string())
+    }
+}
+
+struct Options {
+    port: Option<String>,
+    json: bool,
+    record: Option<String>,
+}
+
+struct Panel {
+    tty: Recorder<TTYPort, Box<dyn Write + Send>>,
+    protocol: ReportReader,
+    read_buf: [u8; MAX_SERIAL_MESSAGE_LEN],
+}
+
+impl Panel {
+    fn open(options: &Options) -> Result<Self, Failure> {
+        let port = options.port.as_ref().ok_or_else(|| {
+            Failure::Usage("no port given, use --port or set PANEL_PORT".to_string())
+        })?;
+        let tty = tty::open(Path::new(port), TTY_TIMEOUT)?;
+
+        let capture: Box<dyn Write + Send> = match &options.record {
+            Some(path) => Box::new(File::create(path)?),
+            None => Box::new(io::sink()),
+        };
+        let tty = Recorder::new(tty, capture)?;
+
+        Ok(Self { tty, protocol: ReportReader::new(), read_buf: [0u8; MAX_SERIAL_MESSAGE_LEN] })
+    }
+
+    fn send(&mut self, command: &Command) -> Result<(), Failure> {
+        self.tty.write_all(&command.as_arrayvec())?;
+        Ok(())
+    }
+
+    fn poll(&mut self) -> Result<ArrayVec<Report, REPORT_QUEUE_SIZE>, Failure> {
+        match self.tty.read(&mut self.read_buf) {
+            Ok(0) => Err(Failure::Io(io::ErrorKind::UnexpectedEof.into())),
+            Ok(count) => self
+                .protocol
+                .process_bytes(&self.read_buf[..count])
+                .map_err(|e| Failure::Protocol(format!("failed to process bytes: {e:?}"))),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => Ok(ArrayVec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn print_usage(program: &str) {
+    println!("Usage: {program} [--port <tty_port>] [--json] [--record <capture_file>] <command>");
+    println!();
+    println!("Commands sent to the panel:");
+    println!("{}", text::SYNTAX);
+    println!();
+    println!("Other commands:");
+    println!("    monitor    print every Report the panel sends");
+    println!("    shell      send Commands typed or piped in the RON format, and print Reports");
+    println!("    info       print information about the connection, protocol and panel targets");
+    println!("    run <script_file>");
+    println!("               run a script of timed commands, see src/script.rs for the syntax");
+    println!("    selftest   run the factory self-test, prompting for the dial and button");
+    println!();
+    println!("Options:");
+    println!("    --port     the panel's TTY, defaults to the PANEL_PORT environment variable");
+    println!("    --json     print output as JSON, one object per line");
+    println!("    --record   record all traffic to a capture file, for attaching to bug reports");
+    println!();
+    println!("Exit codes:");
+    println!("    {EXIT_PROTOCOL_ERROR}  the panel sent malformed data");
+    println!("    {EXIT_USAGE_ERROR}  invalid arguments");
+    println!("    {EXIT_IO_ERROR}  the panel could not be reached");
+    println!("    {EXIT_SCRIPT_FAILED}  a script timed out waiting for the panel");
+    println!("    {EXIT_SELF_TEST_FAILED}  the self-test found a fault");
+}
+
+fn print_report(report: &Report, options: &Options) {
+    if options.json {
+        println!("{}", json!({ "report": report }));
+    } else {
+        println!("Received: {report:?}");
+    }
+}
+
+fn send(command: Command, options: &Options) -> Result<(), Failure> {
+    let mut panel = Panel::open(options)?;
+    panel.send(&command)?;
+
+    if options.json {
+        println!("{}", json!({ "sent": command }));
+    } else {
+        println!("Sent: {command:?}");
+    }
+
+    // Print the value a setting ended up with, which differs from the one sent if the panel
+    // rejected it.
+    if let Command::SetConfig { key, .. } | Command::GetConfig { key } = command {
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        while Instant::now() < deadline {
+            for report in panel.poll()? {
+                if matches!(report, Report::ConfigValue { key: reply_key, .. } if reply_key == key)
+                {
+                    print_report(&report, options);
+                    return Ok(());
+                }
+            }
+        }
+        return Err(Failure::Protocol(format!(
+            "the panel didn't send the value of {}",
+            key.name()
+        )));
+    }
+
+    // There's a reply for each fan or sensor, and how many there are isn't known here, so print
+    // them until the panel goes quiet.
+    if matches!(command, Command::GetFanStatus { .. } | Command::GetThermal) {
+        let is_reply = |report: &&Report| match command {
+            Command::GetThermal => matches!(report, Report::Thermal { .. }),
+            _ => matches!(report, Report::FanStatus { .. }),
+        };
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        let mut received = false;
+        while Instant::now() < deadline {
+            let reports = panel.poll()?;
+            if reports.is_empty() && received {
+                return Ok(());
+            }
+            for report in reports.iter().filter(is_reply) {
+                print_report(report, options);
+                received = true;
+            }
+        }
+        if !received {
+            return Err(Failure::Protocol(format!("the panel didn't reply to {command:?}")));
+        }
+    }
+    Ok(())
+}
+
+fn monitor(options: &Options) -> Result<(), Failure> {
+    let mut panel = Panel::open(options)?;
+    loop {
+        for report in panel.poll()? {
+            print_report(&report, options);
+        }
+    }
+}
+
+fn shell(options: &Options) -> Result<(), Failure> {
+    let panel = Arc::new(Mutex::new(Panel::open(options)?));
+
+    let poller = thread::spawn({
+        let panel = panel.clone();
+        let json = options.json;
+        move || -> Result<(), Failure> {
+            let options = Options { port: None, json, record: None };
+            loop {
+                let reports = panel.lock().unwrap().poll()?;
+                for report in reports {
+                    print_report(&report, &options);
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    });
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if poller.is_finished() {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match ron::de::from_str::<Command>(&line) {
+            Ok(command) => {
+                panel.lock().unwrap().send(&command)?;
+                if options.json {
+                    println!("{}", json!({ "sent": command }));
+                } else {
+                    println!("Sent: {command:?}");
+                }
+            },
+            Err(e) => eprintln!("Failed to parse \"{}\": {e}", line.trim_end()),
+        }
+    }
+
+    if poller.is_finished() {
+        poller.join().unwrap()?;
+    }
+    Ok(())
+}
+
+fn run_script(path: &str, options: &Options) -> Result<(), Failure> {
+    let script: Script = std::fs::read_to_string(path)?
+        .parse()
+        .map_err(|e| Failure::Usage(format!("invalid script {path}: {e}")))?;
+    let mut panel = Panel::open(options)?;
+
+    let print_message = |message| match message {
+        Message::Report(report) => print_report(&report, options),
+        Message::Command(command) if options.json => println!("{}", json!({ "sent": command })),
+        Message::Command(command) => println!("Sent: {command:?}"),
+    };
+    script.run(&mut panel.tty, print_message).map_err(|e| match e {
+        ScriptError::Io(e) => Failure::Io(e),
+        ScriptError::TimedOut(_) => Failure::ScriptFailed(e.to_string()),
+    })
+}
+
+fn self_test(options: &Options) -> Result<(), Failure> {
+    let mut panel = Panel::open(options)?;
+
+    let prompt = |prompt: &str| {
+        if options.json {
+            println!("{}", json!({ "prompt": prompt }));
+        } else {
+            println!("{prompt}...");
+        }
+    };
+    let report = SelfTest::default().run(&mut panel.tty, prompt)?;
+
+    if options.json {
+        let checks: Vec<_> = report
+            .checks
+            .iter()
+            .map(|check| json!({ "name": check.name, "passed": check.passed }))
+            .collect();
+        println!("{}", json!({ "checks": checks, "passed": report.passed() }));
+    } else {
+        println!("{report}");
+    }
+
+    if report.passed() {
+        Ok(())
+    } else {
+        Err(Failure::SelfTestFailed)
+    }
+}
+
+fn info(options: &Options) -> Result<(), Failure> {
+    let port = options.port.clone().unwrap_or_default();
+    let mut panel = Panel::open(options)?;
+    // Panels with older firmware don't answer, which isn't an error here.
+    let targets = discovery::list_targets(&mut panel.tty, LIST_TARGETS_TIMEOUT).ok();
+
+    let version = env!("CARGO_PKG_VERSION");
+    if options.json {
+        let targets = targets.map(|targets| {
+            let lights: Vec<_> = targets
+                .lights
+                .iter()
+                .map(|light| {
+                    json!({
+                        "target": light.target,
+                        "name": light.name,
+                        "brightness": [light.brightness.start(), light.brightness.end()],
+                        "temperature": [light.temperature.start(), light.temperature.end()],
+                    })
+                })
+                .collect();
+            let fans: Vec<_> = targets
+                .fans
+                .iter()
+                .map(|fan| {
+                    json!({
+                        "target": fan.target,
+                        "name": fan.name,
+                        "speed": [fan.speed.start(), fan.speed.end()],
+                    })
+                })
+                .collect();
+            let ring = targets
+                .ring
+                .map(|ring| json!({ "name": ring.name, "pixel_count": ring.pixel_count }));
+            json!({ "lights": lights, "fans": fans, "ring": ring })
+        });
+        println!(
+            "{}",
+            json!({
+                "port": port,
+                "baud_rate": 115200,
+                "protocol_version": version,
+                "max_command_len": MAX_COMMAND_LEN,
+                "max_report_len": MAX_REPORT_LEN,
+                "targets": targets,
+            })
+        );
+    } else {
+        println!("Port:               {port} (115200 baud)");
+        println!("Protocol version:   {version}");
+        println!("Max command length: {MAX_COMMAND_LEN} bytes");
+        println!("Max report length:  {MAX_REPORT_LEN} bytes");
+        match targets {
+            Some(targets) => {
+                for light in &targets.lights {
+                    println!(
+                        "Light {} ({}): brightness {}-{}, temperature {}-{}",
+                        text::format_target(light.target),
+                        light.name,
+                        light.brightness.start(),
+                        light.brightness.end(),
+                        light.temperature.start(),
+                        light.temperature.end(),
+                    );
+                }
+                for fan in &targets.fans {
+                    println!(
+                        "Fan {} ({}): speed {}-{}",
+                        text::format_target(fan.target),
+                        fan.name,
+                        fan.speed.start(),
+                        fan.speed.end(),
+                    );
+                }
+                if let Some(ring) = &targets.ring {
+                    println!("LED ring ({}): {} pixels", ring.name, ring.pixel_count);
+                }
+            },
+            None => println!("Targets:            unknown, the panel didn't list them"),
+        }
+    }
+    Ok(())
+}
+
+fn run(words: &[&str], options: &Options) -> Result<(), Failure> {
+    match words {
+        ["monitor"] => monitor(options),
+        ["shell"] => shell(options),
+        ["info"] => info(options),
+        ["run", path] => run_script(path, options),
+        ["selftest"] => self_test(options),
+        _ => send(text::parse_command(words)?, options),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut options = Options { port: env::var("PANEL_PORT").ok(), json: false, record: None };
+    let mut words = Vec::new();
+    let mut args_iter = args[1..].iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--port" => options.port = args_iter.next().cloned(),
+            "--record" => options.record = args_iter.next().cloned(),
+            "--json" => options.json = true,
+            "-h" | "--help" | "help" => {
+                print_usage(&args[0]);
+                return;
+            },
+            _ => words.push(arg.as_str()),
+        }
+    }
+
+    if words.is_empty() {
+        print_usage(&args[0]);
+        process::exit(EXIT_USAGE_ERROR);
+    }
+
+    let (exit_code, message) = match run(&words, &options) {
+        Ok(()) => return,
+        Err(Failure::Usage(message)) => (EXIT_USAGE_ERROR, message),
+        Err(Failure::Io(e)) => (EXIT_IO_ERROR, e.to_string()),
+        Err(Failure::Protocol(message)) => (EXIT_PROTOCOL_ERROR, message),
+        Err(Failure::ScriptFailed(message)) => (EXIT_SCRIPT_FAILED, message),
+        // The report is already printed.
+        Err(Failure::SelfTestFailed) => process::exit(EXIT_SELF_TEST_FAILED),
+    };
+    if options.json {
+        println!("{}", json!({ "error": message, "exit_code": exit_code }));
+    } else {
+        eprintln!("Error: {message}");
+    }
+    process::exit(exit_code);
+}
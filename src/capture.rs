@@ -0,0 +1,318 @@
+//! A file format for recordings of panel traffic, and a recorder that produces them.
+//!
+//! A capture is a text file with one message per line, so it can be attached to a bug report
+//! and read without any tooling. Columns are separated by a single tab, shown as `<TAB>` here:
+//!
+//! ```text
+//! # panel-protocol capture v1
+//! 0.000000<TAB>><TAB>4200ff00<TAB>Brightness { target: Single(0), value: 65280 }
+//! 0.120513<TAB><<TAB>5601<TAB>DialValue { diff: 1 }
+//! ```
+//!
+//! The columns are the time since the recording started, the direction (`>` from
+//! the host to the panel, `<` from the panel to the host), the raw bytes in hex, and the decoded
+//! message. The raw bytes are authoritative; the last column is only there for humans and is
+//! ignored when a capture is read back. Bytes that don't decode are recorded one at a time with
+//! `malformed` in place of a message.
+
+use crate::{Command, Error, Report};
+use std::{
+    fmt,
+    io::{self, BufRead, Read, Write},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+pub const HEADER: &str = "# panel-protocol capture v1";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Commands sent by the host.
+    ToPanel,
+    /// Reports sent by the panel.
+    ToHost,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Message {
+    Command(Command),
+    Report(Report),
+}
+
+impl Message {
+    /// Parse the message at the start of `buf`, as either a `Command` or a `Report` depending on
+    /// which way it travelled.
+    pub fn try_from(direction: Direction, buf: &[u8]) -> Result<Option<(Message, usize)>, Error> {
+        Ok(match direction {
+            Direction::ToPanel => {
+                Command::try_from(buf)?.map(|(command, len)| (Message::Command(command), len))
+            },
+            Direction::ToHost => {
+                Report::try_from(buf)?.map(|(report, len)| (Message::Report(report), len))
+            },
+        })
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Command(command) => write!(f, "{command:?}"),
+            Message::Report(report) => write!(f, "{report:?}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// Time since the start of the recording.
+    pub timestamp: Duration,
+    pub direction: Direction,
+    pub bytes: Vec<u8>,
+    /// The message `bytes` decode to, or `None` if they are malformed.
+    pub message: Option<Message>,
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::ToPanel => '>',
+            Direction::ToHost => '<',
+        };
+        write!(
+            f,
+            "{}.{:06}\t{direction}\t",
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_micros()
+        )?;
+        for byte in &self.bytes {
+            write!(f, "{byte:02x}")?;
+        }
+        match &self.message {
+            Some(message) => write!(f, "\t{message}"),
+            None => write!(f, "\tmalformed"),
+        }
+    }
+}
+
+impl FromStr for Record {
+    type Err = io::Error;
+
+    fn from_str(line: &str) -> io::Result<Self> {
+        let invalid = |reason: &str| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{reason} in \"{line}\""))
+        };
+
+        let mut columns = line.split('\t');
+        let timestamp =
+            columns.next().and_then(parse_timestamp).ok_or_else(|| invalid("invalid timestamp"))?;
+        let direction = match columns.next() {
+            Some(">") => Direction::ToPanel,
+            Some("<") => Direction::ToHost,
+            _ => return Err(invalid("invalid direction")),
+        };
+        let bytes = columns.next().and_then(parse_hex).ok_or_else(|| invalid("invalid bytes"))?;
+
+        let message = match Message::try_from(direction, &bytes) {
+            Ok(Some((message, len))) if len == bytes.len() => Some(message),
+            _ => None,
+        };
+
+        Ok(Record { timestamp, direction, bytes, message })
+    }
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    let (secs, micros) = timestamp.split_once('.')?;
+    if micros.len() != 6 {
+        return None;
+    }
+
+    Some(Duration::from_secs(secs.parse().ok()?) + Duration::from_micros(micros.parse().ok()?))
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// Read every record from a capture.
+pub fn read<R: BufRead>(input: R) -> io::Result<Vec<Record>> {
+    let mut lines = input.lines();
+    match lines.next() {
+        Some(Ok(header)) if header == HEADER => {},
+        Some(Err(e)) => return Err(e),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "not a panel capture")),
+    }
+
+    lines
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.is_empty() || line.starts_with('#')))
+        .map(|line| line?.parse())
+        .collect()
+}
+
+/// Splits one direction of traffic into messages as the bytes come in.
+struct Splitter {
+    direction: Direction,
+    buf: Vec<u8>,
+}
+
+impl Splitter {
+    fn new(direction: Direction) -> Self {
+        Self { direction, buf: Vec::new() }
+    }
+
+    fn push(&mut self, bytes: &[u8], timestamp: Duration) -> Vec<Record> {
+        self.buf.extend_from_slice(bytes);
+
+        let mut records = Vec::new();
+        loop {
+            let (len, message) = match Message::try_from(self.direction, &self.buf) {
+                Ok(Some((message, len))) => (len, Some(message)),
+                Ok(None) => break,
+                // Skip a single byte and try to resynchronize on the next one.
+                Err(_) => (1, None),
+            };
+
+            let bytes = self.buf.drain(..len).collect();
+            records.push(Record { timestamp, direction: self.direction, bytes, message });
+        }
+        records
+    }
+}
+
+/// Wraps the transport to a panel, and records everything that goes through it.
+///
+/// The recorder can be used anywhere the transport was: writes are recorded as commands and
+/// reads as reports.
+pub struct Recorder<T, W: Write> {
+    transport: T,
+    output: W,
+    start: Instant,
+    commands: Splitter,
+    reports: Splitter,
+}
+
+impl<T, W: Write> Recorder<T, W> {
+    pub fn new(transport: T, mut output: W) -> io::Result<Self> {
+        writeln!(output, "{HEADER}")?;
+        output.flush()?;
+
+        Ok(Self {
+            transport,
+            output,
+            start: Instant::now(),
+            commands: Splitter::new(Direction::ToPanel),
+            reports: Splitter::new(Direction::ToHost),
+        })
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    fn record(&mut self, direction: Direction, bytes: &[u8]) -> io::Result<()> {
+        let timestamp = self.start.elapsed();
+        let splitter = match direction {
+            Direction::ToPanel => &mut self.commands,
+            Direction::ToHost => &mut self.reports,
+        };
+
+        for record in splitter.push(bytes, timestamp) {
+            writeln!(self.output, "{record}")?;
+        }
+        // Flush right away, the recording is most interesting when something crashes.
+        self.output.flush()
+    }
+}
+
+impl<T: Read, W: Write> Read for Recorder<T, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.transport.read(buf)?;
+        self.record(Direction::ToHost, &buf[..count])?;
+        Ok(count)
+    }
+}
+
+impl<T: Write, W: Write> Write for Recorder<T, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = self.transport.write(buf)?;
+        self.record(Direction::ToPanel, &buf[..count])?;
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.transport.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{loopback, PulseMode};
+
+    #[test]
+    fn record_roundtrips_text() {
+        let records = [
+            Record {
+                timestamp: Duration::from_micros(1_234_567),
+                direction: Direction::ToPanel,
+                bytes: Command::Bootload.as_arrayvec().to_vec(),
+                message: Some(Message::Command(Command::Bootload)),
+            },
+            Record {
+                timestamp: Duration::from_micros(2_000_001),
+                direction: Direction::ToHost,
+                bytes: Report::DialValue { diff: -2 }.as_arrayvec().to_vec(),
+                message: Some(Message::Report(Report::DialValue { diff: -2 })),
+            },
+            Record {
+                timestamp: Duration::ZERO,
+                direction: Direction::ToHost,
+                bytes: vec![0xff],
+                message: None,
+            },
+        ];
+
+        for record in &records {
+            assert_eq!(&record.to_string().parse::<Record>().unwrap(), record);
+        }
+    }
+
+    #[test]
+    fn recorder_captures_both_directions() {
+        let (host, mut panel) = loopback::pair();
+        let mut capture = Vec::new();
+        let mut recorder = Recorder::new(host, &mut capture).unwrap();
+
+        let command = Command::Led { r: 1, g: 2, b: 3, pulse_mode: PulseMode::Solid };
+        // Split the command across writes, the recorder still sees a single message.
+        let bytes = command.as_arrayvec();
+        recorder.write_all(&bytes[..3]).unwrap();
+        recorder.write_all(&bytes[3..]).unwrap();
+
+        panel.write_all(&[b'P', 0xff]).unwrap();
+        let mut buf = [0u8; 8];
+        assert_eq!(recorder.read(&mut buf).unwrap(), 2);
+        drop(recorder);
+
+        let records = read(&capture[..]).unwrap();
+        let messages: Vec<_> = records.iter().map(|record| record.message).collect();
+        assert_eq!(
+            messages,
+            [Some(Message::Command(command)), Some(Message::Report(Report::Press)), None]
+        );
+        assert_eq!(records[2].bytes, [0xff]);
+    }
+}
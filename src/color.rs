@@ -0,0 +1,296 @@
+//! Colors for the status LED, so it can be tinted to match the room lights and every build of
+//! the firmware and host tools shows the same color for the same input.
+//!
+//! Everything here is integer math, so it can run on panels without an FPU.
+//!
+//! ```
+//! use panel_protocol::{color::Rgb, units::Kelvin, Command, PulseMode};
+//!
+//! let tint = Rgb::from_kelvin(Kelvin::WARM_WHITE).gamma_corrected();
+//! let command = Command::led(tint, PulseMode::Solid);
+//! assert_eq!(command, Command::Led { r: 255, g: 99, b: 23, pulse_mode: PulseMode::Solid });
+//! ```
+
+use crate::{units::Kelvin, Command, PulseMode};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// A color as hue (in degrees, wrapping at 360), saturation and value, which is easier to sweep
+/// through or dim than `Rgb`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Hsv {
+    pub hue: u16,
+    pub saturation: u8,
+    pub value: u8,
+}
+
+// The color of a black body at each temperature, from Tanner Helland's fit of the CIE 1964 data.
+// Temperatures in between are interpolated.
+const BLACK_BODY: [(u16, Rgb); 27] = [
+    (1000, Rgb::new(255, 68, 0)),
+    (1500, Rgb::new(255, 108, 0)),
+    (2000, Rgb::new(255, 137, 14)),
+    (2500, Rgb::new(255, 159, 70)),
+    (3000, Rgb::new(255, 177, 110)),
+    (3500, Rgb::new(255, 193, 141)),
+    (4000, Rgb::new(255, 206, 166)),
+    (4500, Rgb::new(255, 218, 187)),
+    (5000, Rgb::new(255, 228, 206)),
+    (5500, Rgb::new(255, 237, 222)),
+    (6000, Rgb::new(255, 246, 237)),
+    (6500, Rgb::new(255, 254, 250)),
+    (7000, Rgb::new(243, 242, 255)),
+    (7500, Rgb::new(230, 235, 255)),
+    (8000, Rgb::new(221, 230, 255)),
+    (8500, Rgb::new(215, 226, 255)),
+    (9000, Rgb::new(210, 223, 255)),
+    (9500, Rgb::new(205, 220, 255)),
+    (10000, Rgb::new(202, 218, 255)),
+    (11000, Rgb::new(196, 214, 255)),
+    (12000, Rgb::new(191, 211, 255)),
+    (14000, Rgb::new(184, 207, 255)),
+    (16000, Rgb::new(179, 203, 255)),
+    (20000, Rgb::new(171, 198, 255)),
+    (25000, Rgb::new(164, 194, 255)),
+    (30000, Rgb::new(159, 190, 255)),
+    (40000, Rgb::new(152, 186, 255)),
+];
+
+// round(255 * (i / 255)^2.2)
+#[rustfmt::skip]
+const GAMMA: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2,
+    3, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 6, 6, 6,
+    6, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 11, 11, 11, 12,
+    12, 13, 13, 13, 14, 14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19,
+    20, 20, 21, 22, 22, 23, 23, 24, 25, 25, 26, 26, 27, 28, 28, 29,
+    30, 30, 31, 32, 33, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41,
+    42, 43, 43, 44, 45, 46, 47, 48, 49, 49, 50, 51, 52, 53, 54, 55,
+    56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71,
+    73, 74, 75, 76, 77, 78, 79, 81, 82, 83, 84, 85, 87, 88, 89, 90,
+    91, 93, 94, 95, 97, 98, 99, 100, 102, 103, 105, 106, 107, 109, 110, 111,
+    113, 114, 116, 117, 119, 120, 121, 123, 124, 126, 127, 129, 130, 132, 133, 135,
+    137, 138, 140, 141, 143, 145, 146, 148, 149, 151, 153, 154, 156, 158, 159, 161,
+    163, 165, 166, 168, 170, 172, 173, 175, 177, 179, 181, 182, 184, 186, 188, 190,
+    192, 194, 196, 197, 199, 201, 203, 205, 207, 209, 211, 213, 215, 217, 219, 221,
+    223, 225, 227, 229, 231, 234, 236, 238, 240, 242, 244, 246, 248, 251, 253, 255,
+];
+
+fn lerp(from: u8, to: u8, numerator: u32, denominator: u32) -> u8 {
+    let (from, to) = (i64::from(from), i64::from(to));
+    (from + (to - from) * i64::from(numerator) / i64::from(denominator)) as u8
+}
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb::new(0, 0, 0);
+    pub const WHITE: Rgb = Rgb::new(255, 255, 255);
+
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// The color of a light at `kelvin`, at full brightness.
+    pub fn from_kelvin(kelvin: Kelvin) -> Self {
+        let kelvin = kelvin.get();
+        let upper = BLACK_BODY.iter().position(|&(k, _)| k >= kelvin).unwrap_or(0);
+        let (upper_kelvin, upper_color) = BLACK_BODY[upper];
+        if upper == 0 || upper_kelvin == kelvin {
+            return upper_color;
+        }
+
+        let (lower_kelvin, lower_color) = BLACK_BODY[upper - 1];
+        let numerator = u32::from(kelvin - lower_kelvin);
+        let denominator = u32::from(upper_kelvin - lower_kelvin);
+        Self::new(
+            lerp(lower_color.r, upper_color.r, numerator, denominator),
+            lerp(lower_color.g, upper_color.g, numerator, denominator),
+            lerp(lower_color.b, upper_color.b, numerator, denominator),
+        )
+    }
+
+    /// The color dimmed to `level`, where 255 leaves it unchanged.
+    pub fn scaled(self, level: u8) -> Self {
+        let scale = |channel: u8| ((u16::from(channel) * u16::from(level) + 127) / 255) as u8;
+        Self::new(scale(self.r), scale(self.g), scale(self.b))
+    }
+
+    /// Map perceived intensity to LED duty cycle with a gamma of 2.2, so that e.g. half of
+    /// 255 looks half as bright. Apply it once, just before the color is sent.
+    pub fn gamma_corrected(self) -> Self {
+        Self::new(
+            GAMMA[usize::from(self.r)],
+            GAMMA[usize::from(self.g)],
+            GAMMA[usize::from(self.b)],
+        )
+    }
+}
+
+impl From<Hsv> for Rgb {
+    fn from(hsv: Hsv) -> Self {
+        let hue = u32::from(hsv.hue % 360);
+        let saturation = u32::from(hsv.saturation);
+        let value = u32::from(hsv.value);
+
+        // How far through the current 60 degree sector the hue is, out of 60.
+        let offset = hue % 60;
+        let p = (value * (255 - saturation) / 255) as u8;
+        let q = (value * (255 * 60 - saturation * offset) / (255 * 60)) as u8;
+        let t = (value * (255 * 60 - saturation * (60 - offset)) / (255 * 60)) as u8;
+        let v = hsv.value;
+
+        match hue / 60 {
+            0 => Rgb::new(v, t, p),
+            1 => Rgb::new(q, v, p),
+            2 => Rgb::new(p, v, t),
+            3 => Rgb::new(p, q, v),
+            4 => Rgb::new(t, p, v),
+            _ => Rgb::new(v, p, q),
+        }
+    }
+}
+
+impl From<Rgb> for Hsv {
+    fn from(rgb: Rgb) -> Self {
+        let (r, g, b) = (i32::from(rgb.r), i32::from(rgb.g), i32::from(rgb.b));
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        if delta == 0 {
+            return Hsv { hue: 0, saturation: 0, value: max as u8 };
+        }
+
+        let hue = if max == r {
+            60 * (g - b) / delta
+        } else if max == g {
+            120 + 60 * (b - r) / delta
+        } else {
+            240 + 60 * (r - g) / delta
+        };
+        Hsv {
+            hue: hue.rem_euclid(360) as u16,
+            saturation: ((delta * 255 + max / 2) / max) as u8,
+            value: max as u8,
+        }
+    }
+}
+
+/// How the panel turns `Led` colors into LED output, set with `Command::LedConfig`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LedConfig {
+    /// Scales every color, where 255 leaves colors unchanged.
+    pub global_brightness: u8,
+    /// The gamma in tenths, e.g. 22 for 2.2. Values below 10 are treated as 10, which shows
+    /// colors as they are.
+    pub gamma: u8,
+}
+
+impl LedConfig {
+    pub const LINEAR_GAMMA: u8 = 10;
+    pub const STANDARD_GAMMA: u8 = 22;
+
+    /// The 16-bit PWM duty cycle of each LED channel when showing `color`.
+    ///
+    /// Dimming and gamma correction are done at 16 bits, so colors stay distinct at low
+    /// brightness where they wouldn't if dimmed as `Rgb`. Fractional gammas are approximated by
+    /// blending the neighbouring whole powers.
+    pub fn duty(&self, color: Rgb) -> [u16; 3] {
+        let gamma = self.gamma.max(Self::LINEAR_GAMMA);
+        let brightness = u32::from(self.global_brightness);
+        let channel = |value: u8| {
+            // The dimmed value out of u16::MAX, since 255 * 255 * 257 / 255 = u16::MAX.
+            let linear = u32::from(value) * brightness * 257 / 255;
+            let lower = power(linear, gamma / 10);
+            let upper = power(linear, gamma / 10 + 1);
+            (lower - (lower - upper) * u32::from(gamma % 10) / 10) as u16
+        };
+        [channel(color.r), channel(color.g), channel(color.b)]
+    }
+}
+
+/// The panel's configuration when it starts, which shows `Led` colors as they are.
+impl Default for LedConfig {
+    fn default() -> Self {
+        Self { global_brightness: 255, gamma: Self::LINEAR_GAMMA }
+    }
+}
+
+/// `value` out of `u16::MAX`, raised to `exponent`.
+fn power(value: u32, exponent: u8) -> u32 {
+    (1..exponent).fold(value, |result, _| result * value / u32::from(u16::MAX))
+}
+
+impl Command {
+    pub fn led(color: Rgb, pulse_mode: PulseMode) -> Self {
+        Command::Led { r: color.r, g: color.g, b: color.b, pulse_mode }
+    }
+
+    pub fn led_config(config: LedConfig) -> Self {
+        Command::LedConfig { global_brightness: config.global_brightness, gamma: config.gamma }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_kelvin() {
+        assert_eq!(Rgb::from_kelvin(Kelvin::MIN), Rgb::new(255, 68, 0));
+        assert_eq!(Rgb::from_kelvin(Kelvin::DAYLIGHT), Rgb::new(255, 254, 250));
+        assert_eq!(Rgb::from_kelvin(Kelvin::MAX), Rgb::new(152, 186, 255));
+        // Halfway between the 2500K and 3000K entries.
+        assert_eq!(Rgb::from_kelvin(Kelvin::new(2750).unwrap()), Rgb::new(255, 168, 90));
+    }
+
+    #[test]
+    fn converts_hsv() {
+        let colors = [
+            (Hsv { hue: 0, saturation: 255, value: 255 }, Rgb::new(255, 0, 0)),
+            (Hsv { hue: 60, saturation: 255, value: 255 }, Rgb::new(255, 255, 0)),
+            (Hsv { hue: 120, saturation: 255, value: 255 }, Rgb::new(0, 255, 0)),
+            (Hsv { hue: 180, saturation: 255, value: 255 }, Rgb::new(0, 255, 255)),
+            (Hsv { hue: 240, saturation: 255, value: 255 }, Rgb::new(0, 0, 255)),
+            (Hsv { hue: 300, saturation: 255, value: 255 }, Rgb::new(255, 0, 255)),
+            (Hsv { hue: 0, saturation: 0, value: 128 }, Rgb::new(128, 128, 128)),
+        ];
+        for &(hsv, rgb) in &colors {
+            assert_eq!(Rgb::from(hsv), rgb);
+            assert_eq!(Hsv::from(rgb), hsv);
+        }
+
+        assert_eq!(Rgb::from(Hsv { hue: 390, saturation: 255, value: 255 }), Rgb::new(255, 127, 0));
+        assert_eq!(Rgb::from(Hsv { hue: 30, saturation: 255, value: 255 }), Rgb::new(255, 127, 0));
+    }
+
+    #[test]
+    fn scales_and_corrects_gamma() {
+        assert_eq!(Rgb::WHITE.scaled(128), Rgb::new(128, 128, 128));
+        assert_eq!(Rgb::new(10, 20, 30).scaled(0), Rgb::BLACK);
+        assert_eq!(Rgb::new(0, 128, 255).gamma_corrected(), Rgb::new(0, 56, 255));
+    }
+
+    #[test]
+    fn computes_led_duty() {
+        let color = Rgb::new(0, 128, 255);
+        assert_eq!(LedConfig::default().duty(color), [0, 32896, 65535]);
+
+        let dim = LedConfig { global_brightness: 1, gamma: LedConfig::LINEAR_GAMMA };
+        assert_eq!(dim.duty(Rgb::new(1, 2, 255)), [1, 2, 257]);
+
+        let standard = LedConfig { global_brightness: 255, gamma: LedConfig::STANDARD_GAMMA };
+        assert_eq!(standard.duty(color), [0, 14868, 65535]);
+        let squared = LedConfig { global_brightness: 255, gamma: 20 };
+        assert_eq!(squared.duty(color), [0, 16512, 65535]);
+    }
+}
@@ -0,0 +1,380 @@
+//! Settings stored on the panel, which replace defaults that used to be hardcoded in the
+//! firmware.
+//!
+//! Each setting is read with `GetConfig` and changed with `SetConfig`, and the panel answers both
+//! with a `ConfigValue` holding the value in effect. Changes apply immediately but are only kept
+//! across restarts once `SaveConfig` is sent, and `FactoryReset` goes back to `Config::FACTORY`.
+//!
+//! The settings also decide what the panel does on its own: it applies `power_on_commands` when
+//! it starts, and `offline_commands` once the host has been quiet for too long, see `Watchdog`.
+//! While it's offline it also keeps every fan at `offline_fan_speed` or faster, see
+//! `Config::offline_fan_duty`.
+//!
+//! ```
+//! use panel_protocol::{
+//!     color::Rgb,
+//!     config::{Config, ConfigKey},
+//!     Command,
+//! };
+//!
+//! let mut config = Config::FACTORY;
+//! let command = Command::SetConfig { key: ConfigKey::DefaultLedColor, value: 0x00ff_8000 };
+//! if let Command::SetConfig { key, value } = command {
+//!     assert!(config.set(key, value));
+//! }
+//! assert_eq!(config.default_led_color, Rgb::new(255, 128, 0));
+//! assert!(!config.set(ConfigKey::DialSensitivity, 0));
+//! ```
+
+use crate::{color::Rgb, units::Duty, Command, Error, PulseMode, Target};
+use core::convert::TryFrom;
+
+/// A setting of `Config`, sent as a single byte.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigKey {
+    DialSensitivity,
+    DefaultLedColor,
+    PowerOnBrightness,
+    HeartbeatInterval,
+    PowerOnFanSpeed,
+    OfflineBrightness,
+    OfflineFade,
+    OfflineFanSpeed,
+    FanStatusInterval,
+    ThermalInterval,
+}
+
+impl ConfigKey {
+    pub const ALL: [ConfigKey; 10] = [
+        ConfigKey::DialSensitivity,
+        ConfigKey::DefaultLedColor,
+        ConfigKey::PowerOnBrightness,
+        ConfigKey::HeartbeatInterval,
+        ConfigKey::PowerOnFanSpeed,
+        ConfigKey::OfflineBrightness,
+        ConfigKey::OfflineFade,
+        ConfigKey::OfflineFanSpeed,
+        ConfigKey::FanStatusInterval,
+        ConfigKey::ThermalInterval,
+    ];
+
+    /// The name the command line tools use for the setting.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigKey::DialSensitivity => "dial-sensitivity",
+            ConfigKey::DefaultLedColor => "default-led-color",
+            ConfigKey::PowerOnBrightness => "power-on-brightness",
+            ConfigKey::HeartbeatInterval => "heartbeat-interval",
+            ConfigKey::PowerOnFanSpeed => "power-on-fan-speed",
+            ConfigKey::OfflineBrightness => "offline-brightness",
+            ConfigKey::OfflineFade => "offline-fade",
+            ConfigKey::OfflineFanSpeed => "offline-fan-speed",
+            ConfigKey::FanStatusInterval => "fan-status-interval",
+            ConfigKey::ThermalInterval => "thermal-interval",
+        }
+    }
+}
+
+impl From<ConfigKey> for u8 {
+    fn from(key: ConfigKey) -> Self {
+        match key {
+            ConfigKey::DialSensitivity => b'D',
+            ConfigKey::DefaultLedColor => b'L',
+            ConfigKey::PowerOnBrightness => b'B',
+            ConfigKey::HeartbeatInterval => b'H',
+            ConfigKey::PowerOnFanSpeed => b'F',
+            ConfigKey::OfflineBrightness => b'b',
+            ConfigKey::OfflineFade => b't',
+            ConfigKey::OfflineFanSpeed => b'f',
+            ConfigKey::FanStatusInterval => b'R',
+            ConfigKey::ThermalInterval => b'T',
+        }
+    }
+}
+
+impl TryFrom<u8> for ConfigKey {
+    type Error = Error;
+
+    fn try_from(byte: u8) -> Result<Self, Error> {
+        match byte {
+            b'D' => Ok(ConfigKey::DialSensitivity),
+            b'L' => Ok(ConfigKey::DefaultLedColor),
+            b'B' => Ok(ConfigKey::PowerOnBrightness),
+            b'H' => Ok(ConfigKey::HeartbeatInterval),
+            b'F' => Ok(ConfigKey::PowerOnFanSpeed),
+            b'b' => Ok(ConfigKey::OfflineBrightness),
+            b't' => Ok(ConfigKey::OfflineFade),
+            b'f' => Ok(ConfigKey::OfflineFanSpeed),
+            b'R' => Ok(ConfigKey::FanStatusInterval),
+            b'T' => Ok(ConfigKey::ThermalInterval),
+            _ => Err(Error::MalformedMessage),
+        }
+    }
+}
+
+/// The panel treats the host as gone once this many heartbeat intervals pass without a command.
+pub const MISSED_HEARTBEATS: u32 = 3;
+
+/// Every setting stored on the panel. On the wire each one is a `u32`, see `get` and `set`,
+/// except the offline LED color and pulse mode, which are set with `OfflineLed`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// How many steps a `DialValue` reports for each detent the dial is turned, from 1.
+    pub dial_sensitivity: u8,
+    /// The LED color shown before the host sends a `Led`, sent as `0x00RRGGBB`.
+    pub default_led_color: Rgb,
+    /// The brightness every light starts at.
+    pub power_on_brightness: u16,
+    /// How often the host promises to send a command, or 0 if it doesn't.
+    pub heartbeat_interval_ms: u16,
+    /// The speed every fan starts at, as a duty cycle.
+    pub power_on_fan_speed: u16,
+    /// The brightness every light fades to when the host is gone.
+    pub offline_brightness: u16,
+    pub offline_fade_ms: u16,
+    /// The slowest every fan runs while the host is gone, which should be safe without the host
+    /// watching temperatures. Fans following a curve keep following it above this speed.
+    pub offline_fan_speed: u16,
+    pub offline_led_color: Rgb,
+    pub offline_pulse_mode: PulseMode,
+    /// How often the panel sends a `FanStatus` for every fan, or 0 to only send them on request.
+    pub fan_status_interval_ms: u16,
+    /// How often the panel sends a `Thermal` for every sensor, or 0 to only send them on request.
+    pub thermal_interval_ms: u16,
+}
+
+impl Config {
+    /// The settings a panel ships with, which match what the firmware did before they could be
+    /// changed.
+    pub const FACTORY: Config = Config {
+        dial_sensitivity: 1,
+        default_led_color: Rgb::BLACK,
+        power_on_brightness: 0,
+        heartbeat_interval_ms: 0,
+        power_on_fan_speed: Duty::OFF.0,
+        offline_brightness: 0,
+        offline_fade_ms: 1000,
+        offline_fan_speed: Duty::FULL.0,
+        offline_led_color: Rgb::new(255, 0, 0),
+        offline_pulse_mode: PulseMode::DoublePulse,
+        fan_status_interval_ms: 0,
+        thermal_interval_ms: 0,
+    };
+
+    pub fn get(&self, key: ConfigKey) -> u32 {
+        match key {
+            ConfigKey::DialSensitivity => self.dial_sensitivity.into(),
+            ConfigKey::DefaultLedColor => {
+                let Rgb { r, g, b } = self.default_led_color;
+                u32::from_be_bytes([0, r, g, b])
+            },
+            ConfigKey::PowerOnBrightness => self.power_on_brightness.into(),
+            ConfigKey::HeartbeatInterval => self.heartbeat_interval_ms.into(),
+            ConfigKey::PowerOnFanSpeed => self.power_on_fan_speed.into(),
+            ConfigKey::OfflineBrightness => self.offline_brightness.into(),
+            ConfigKey::OfflineFade => self.offline_fade_ms.into(),
+            ConfigKey::OfflineFanSpeed => self.offline_fan_speed.into(),
+            ConfigKey::FanStatusInterval => self.fan_status_interval_ms.into(),
+            ConfigKey::ThermalInterval => self.thermal_interval_ms.into(),
+        }
+    }
+
+    /// Change a setting, returning `false` and leaving it as it was if `value` is out of range.
+    pub fn set(&mut self, key: ConfigKey, value: u32) -> bool {
+        let field = match key {
+            ConfigKey::DialSensitivity => match u8::try_from(value) {
+                Ok(sensitivity) if sensitivity > 0 => {
+                    self.dial_sensitivity = sensitivity;
+                    return true;
+                },
+                _ => return false,
+            },
+            ConfigKey::DefaultLedColor => match value.to_be_bytes() {
+                [0, r, g, b] => {
+                    self.default_led_color = Rgb::new(r, g, b);
+                    return true;
+                },
+                _ => return false,
+            },
+            ConfigKey::PowerOnBrightness => &mut self.power_on_brightness,
+            ConfigKey::HeartbeatInterval => &mut self.heartbeat_interval_ms,
+            ConfigKey::PowerOnFanSpeed => &mut self.power_on_fan_speed,
+            ConfigKey::OfflineBrightness => &mut self.offline_brightness,
+            ConfigKey::OfflineFade => &mut self.offline_fade_ms,
+            ConfigKey::OfflineFanSpeed => &mut self.offline_fan_speed,
+            ConfigKey::FanStatusInterval => &mut self.fan_status_interval_ms,
+            ConfigKey::ThermalInterval => &mut self.thermal_interval_ms,
+        };
+        match u16::try_from(value) {
+            Ok(value) => {
+                *field = value;
+                true
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// How long the panel waits for a command before it treats the host as gone, or `None` if
+    /// the host doesn't send heartbeats.
+    pub fn offline_timeout_ms(&self) -> Option<u32> {
+        match self.heartbeat_interval_ms {
+            0 => None,
+            interval_ms => Some(u32::from(interval_ms) * MISSED_HEARTBEATS),
+        }
+    }
+
+    /// The commands the panel applies to itself when it starts.
+    pub fn power_on_commands(&self) -> [Command; 3] {
+        [
+            Command::Brightness { target: Target::ALL, value: self.power_on_brightness },
+            Command::FanSpeed { target: Target::ALL, value: self.power_on_fan_speed },
+            Command::led(self.default_led_color, PulseMode::Solid),
+        ]
+    }
+
+    /// The commands the panel applies to itself when the host is gone. The fans aren't set with
+    /// a `FanSpeed`, which would end their curves, see `Config::offline_fan_duty` instead.
+    pub fn offline_commands(&self) -> [Command; 2] {
+        [
+            Command::BrightnessFade {
+                target: Target::ALL,
+                value: self.offline_brightness,
+                transition_ms: self.offline_fade_ms,
+            },
+            Command::led(self.offline_led_color, self.offline_pulse_mode),
+        ]
+    }
+
+    /// The duty cycle a fan runs at while the host is gone, given the one it would otherwise run
+    /// at from its last `FanSpeed` or its curve.
+    pub fn offline_fan_duty(&self, duty: Duty) -> Duty {
+        duty.max(Duty(self.offline_fan_speed))
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::FACTORY
+    }
+}
+
+/// Keeps track of whether the host is still there, on the panel.
+///
+/// Times are in milliseconds from any starting point, and may wrap around like a tick counter.
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    last_command_ms: u32,
+    offline: bool,
+}
+
+impl Watchdog {
+    pub fn new(now_ms: u32) -> Self {
+        Self { last_command_ms: now_ms, offline: false }
+    }
+
+    /// Note that a command arrived at `now_ms`. The panel stays in its offline state until the
+    /// host sets the lights, fans and LED again.
+    pub fn feed(&mut self, now_ms: u32) {
+        self.last_command_ms = now_ms;
+        self.offline = false;
+    }
+
+    /// Whether the host has just gone quiet for longer than `config` allows, in which case the
+    /// panel should apply `Config::offline_commands`. This is only true once until the next
+    /// command arrives.
+    pub fn check(&mut self, config: &Config, now_ms: u32) -> bool {
+        let silent_ms = now_ms.wrapping_sub(self.last_command_ms);
+        if self.offline || config.offline_timeout_ms().is_none_or(|timeout| silent_ms < timeout) {
+            return false;
+        }
+        self.offline = true;
+        true
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fan::{CurvePoint, CurvePoints};
+
+    #[test]
+    fn sets_every_key() {
+        // A value each key accepts, and one it rejects.
+        let cases = [
+            (ConfigKey::DialSensitivity, 3, 0),
+            (ConfigKey::DefaultLedColor, 0x0010_2030, 0x0100_0000),
+            (ConfigKey::PowerOnBrightness, 2048, 65536),
+            (ConfigKey::HeartbeatInterval, 500, 65536),
+            (ConfigKey::PowerOnFanSpeed, 0x8000, 65536),
+            (ConfigKey::OfflineBrightness, 100, 65536),
+            (ConfigKey::OfflineFade, 1500, 65536),
+            (ConfigKey::OfflineFanSpeed, 0xffff, 0x0001_0000),
+            (ConfigKey::FanStatusInterval, 1000, 65536),
+            (ConfigKey::ThermalInterval, 2000, u32::MAX),
+        ];
+        assert_eq!(cases.len(), ConfigKey::ALL.len());
+
+        let mut config = Config::FACTORY;
+        for (&(key, valid, invalid), &expected_key) in cases.iter().zip(&ConfigKey::ALL) {
+            assert_eq!(key, expected_key);
+            assert_eq!(ConfigKey::try_from(u8::from(key)).ok(), Some(key));
+            assert!(config.set(key, valid));
+            assert_eq!(config.get(key), valid);
+            assert!(!config.set(key, invalid));
+            assert_eq!(config.get(key), valid);
+        }
+        assert_eq!(config.default_led_color, Rgb::new(0x10, 0x20, 0x30));
+        assert!(!config.set(ConfigKey::DialSensitivity, 256));
+    }
+
+    #[test]
+    fn keeps_fan_curves_offline() {
+        let config = Config {
+            heartbeat_interval_ms: 100,
+            offline_fan_speed: Duty::from_percent(30).unwrap().0,
+            ..Config::FACTORY
+        };
+        let point = |celsius, percent| CurvePoint { celsius, percent };
+        let curve = CurvePoints::new(&[point(30, 20), point(60, 100)]).unwrap();
+
+        let mut watchdog = Watchdog::new(0);
+        assert!(watchdog.check(&config, 300));
+        assert!(config.offline_commands().iter().all(|command| !matches!(
+            command,
+            Command::FanSpeed { .. } | Command::FanCurve { .. }
+        )));
+
+        // The curve still decides, but the fan never runs slower than the offline speed.
+        let offline_duty =
+            |centi_celsius| config.offline_fan_duty(curve.duty_at(centi_celsius).unwrap());
+        assert_eq!(offline_duty(2500), Duty::from_percent(30).unwrap());
+        assert_eq!(offline_duty(4500), Duty::from_percent(60).unwrap());
+        assert_eq!(offline_duty(7000), Duty::FULL);
+    }
+
+    #[test]
+    fn goes_offline_once_per_silence() {
+        let mut config = Config::FACTORY;
+        let mut watchdog = Watchdog::new(u32::MAX - 100);
+        assert!(!watchdog.check(&config, 1_000_000));
+
+        config.heartbeat_interval_ms = 500;
+        watchdog.feed(u32::MAX - 100);
+        assert!(!watchdog.check(&config, 1398));
+        assert!(watchdog.check(&config, 1399));
+        assert!(!watchdog.check(&config, 5000));
+        assert!(watchdog.is_offline());
+
+        watchdog.feed(6000);
+        assert!(!watchdog.is_offline());
+        assert!(watchdog.check(&config, 7500));
+    }
+}
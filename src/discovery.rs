@@ -0,0 +1,177 @@
+//! Finding out which lights, fans and LED ring a panel controls, from the `TargetInfo` reports it
+//! sends in response to `Command::ListTargets`.
+
+use crate::{stream::ReportStream, Command, Report, Target, TargetKind};
+use std::{
+    io::{self, Read, Write},
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LightInfo {
+    pub target: Target,
+    pub name: String,
+    pub brightness: RangeInclusive<u16>,
+    pub temperature: RangeInclusive<u16>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FanInfo {
+    pub target: Target,
+    pub name: String,
+    pub speed: RangeInclusive<u16>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RingInfo {
+    pub name: String,
+    pub pixel_count: u16,
+}
+
+/// The lights and fans of a panel, in the order the panel listed them, and its LED ring if it
+/// has one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Targets {
+    pub lights: Vec<LightInfo>,
+    pub fans: Vec<FanInfo>,
+    pub ring: Option<RingInfo>,
+}
+
+impl Targets {
+    /// Record the target described by a `TargetInfo` report. Other reports are ignored, and
+    /// `false` is returned for them.
+    pub fn add(&mut self, report: &Report) -> bool {
+        let Report::TargetInfo { target, kind, name, min, max } = *report else {
+            return false;
+        };
+
+        match kind {
+            TargetKind::Brightness | TargetKind::Temperature => {
+                let light = match self.lights.iter_mut().position(|light| light.target == target) {
+                    Some(index) => &mut self.lights[index],
+                    None => {
+                        self.lights.push(LightInfo {
+                            target,
+                            name: name.as_str().to_string(),
+                            brightness: 0..=u16::MAX,
+                            temperature: 0..=u16::MAX,
+                        });
+                        self.lights.last_mut().unwrap()
+                    },
+                };
+                match kind {
+                    TargetKind::Brightness => light.brightness = min..=max,
+                    _ => light.temperature = min..=max,
+                }
+            },
+            TargetKind::FanSpeed => {
+                self.fans.retain(|fan| fan.target != target);
+                self.fans.push(FanInfo {
+                    target,
+                    name: name.as_str().to_string(),
+                    speed: min..=max,
+                });
+            },
+            TargetKind::LedRing => {
+                self.ring = Some(RingInfo {
+                    name: name.as_str().to_string(),
+                    pixel_count: max.saturating_sub(min).saturating_add(1),
+                });
+            },
+        }
+        true
+    }
+}
+
+/// Ask the panel for its targets, waiting up to `timeout` for the full list.
+pub fn list_targets<T: Read + Write>(transport: &mut T, timeout: Duration) -> io::Result<Targets> {
+    transport.write_all(&Command::ListTargets.as_arrayvec())?;
+
+    let mut targets = Targets::default();
+    let mut reports = ReportStream::new(transport);
+    let deadline = Instant::now() + timeout;
+    loop {
+        match reports.next(deadline)? {
+            Some(Report::TargetListDone) => return Ok(targets),
+            Some(report) => {
+                targets.add(&report);
+            },
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the panel did not list its targets",
+                ))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{loopback, TargetName};
+
+    fn info(target: Target, kind: TargetKind, name: &str, min: u16, max: u16) -> Report {
+        Report::TargetInfo { target, kind, name: TargetName::new(name).unwrap(), min, max }
+    }
+
+    #[test]
+    fn lists_targets() {
+        let (mut host, mut panel) = loopback::pair();
+        host.set_read_timeout(Some(Duration::from_millis(10)));
+
+        let reports = [
+            info(Target::FRONT_LIGHTS, TargetKind::Brightness, "Front Lights", 0, 4095),
+            info(Target::FRONT_LIGHTS, TargetKind::Temperature, "Front Lights", 2700, 6500),
+            info(Target::single(0).unwrap(), TargetKind::FanSpeed, "Fan", 0, 1800),
+            Report::DialValue { diff: 1 },
+            info(Target::BACK_LIGHTS, TargetKind::Brightness, "Back Lights", 0, 1023),
+            info(Target::single(0).unwrap(), TargetKind::LedRing, "Dial Ring", 0, 23),
+            Report::TargetListDone,
+        ];
+        for report in &reports {
+            panel.write_all(&report.as_arrayvec()).unwrap();
+        }
+
+        let targets = list_targets(&mut host, Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            targets,
+            Targets {
+                lights: vec![
+                    LightInfo {
+                        target: Target::FRONT_LIGHTS,
+                        name: "Front Lights".to_string(),
+                        brightness: 0..=4095,
+                        temperature: 2700..=6500,
+                    },
+                    LightInfo {
+                        target: Target::BACK_LIGHTS,
+                        name: "Back Lights".to_string(),
+                        brightness: 0..=1023,
+                        temperature: 0..=u16::MAX,
+                    },
+                ],
+                fans: vec![FanInfo {
+                    target: Target::single(0).unwrap(),
+                    name: "Fan".to_string(),
+                    speed: 0..=1800,
+                }],
+                ring: Some(RingInfo { name: "Dial Ring".to_string(), pixel_count: 24 }),
+            }
+        );
+
+        let mut command = [0u8; 1];
+        panel.read_exact(&mut command).unwrap();
+        assert_eq!(Command::try_from(&command).unwrap(), Some((Command::ListTargets, 1)));
+    }
+
+    #[test]
+    fn times_out_on_old_firmware() {
+        let (mut host, _panel) = loopback::pair();
+        host.set_read_timeout(Some(Duration::from_millis(5)));
+
+        let err = list_targets(&mut host, Duration::from_millis(20)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,72 @@
+//! Linear fades between two values, for the firmware to compute the intermediate values of
+//! `BrightnessFade` and `TemperatureFade` on each tick.
+//!
+//! ```
+//! use panel_protocol::fade::Fade;
+//!
+//! // A fade that starts while another is running begins from the current value.
+//! let fade = Fade::new(1000, 2000, 500);
+//! assert_eq!(fade.value_at(250), 1500);
+//! assert_eq!(fade.value_at(800), 2000);
+//! ```
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Fade {
+    from: u16,
+    to: u16,
+    duration_ms: u32,
+}
+
+impl Fade {
+    /// A fade from `from` to `to` over `duration_ms`. A zero duration jumps straight to `to`.
+    pub fn new(from: u16, to: u16, duration_ms: u32) -> Self {
+        Self { from, to, duration_ms }
+    }
+
+    pub fn target(&self) -> u16 {
+        self.to
+    }
+
+    /// The value `elapsed_ms` after the start of the fade.
+    pub fn value_at(&self, elapsed_ms: u32) -> u16 {
+        if self.is_done(elapsed_ms) {
+            return self.to;
+        }
+
+        let delta = i64::from(self.to) - i64::from(self.from);
+        let offset = delta * i64::from(elapsed_ms) / i64::from(self.duration_ms);
+        (i64::from(self.from) + offset) as u16
+    }
+
+    pub fn is_done(&self, elapsed_ms: u32) -> bool {
+        elapsed_ms >= self.duration_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolates_in_both_directions() {
+        let up = Fade::new(0, 4000, 2000);
+        assert_eq!(up.value_at(0), 0);
+        assert_eq!(up.value_at(500), 1000);
+        assert_eq!(up.value_at(1999), 3998);
+        assert_eq!(up.value_at(2000), 4000);
+        assert!(!up.is_done(1999));
+        assert!(up.is_done(2000));
+
+        let down = Fade::new(u16::MAX, 0, 100);
+        assert_eq!(down.value_at(50), 32768);
+        assert_eq!(down.value_at(u32::MAX), 0);
+    }
+
+    #[test]
+    fn zero_duration_jumps_to_target() {
+        let fade = Fade::new(10, 20, 0);
+        assert_eq!(fade.value_at(0), 20);
+        assert!(fade.is_done(0));
+    }
+}
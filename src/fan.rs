@@ -0,0 +1,233 @@
+//! Fan health checks and automatic fan curves shared by the firmware and host tools, so both
+//! agree on when a fan has failed and how fast it should turn.
+//!
+//! The panel measures each fan's speed from its tachometer and reports it with its duty cycle in
+//! `FanStatus`, on request with `GetFanStatus` or every `ConfigKey::FanStatusInterval`.
+//!
+//! A `FanCurve` command has the panel regulate a fan from one of its temperature sensors on its
+//! own, so the fans keep up even if the host hangs. A `FanSpeed` for the fan puts it back under
+//! manual control. Once the panel treats the host as gone, curves keep running but never below
+//! `Config::offline_fan_speed`.
+//!
+//! ```
+//! use panel_protocol::{
+//!     fan::{StallDetector, STALL_TIME_MS},
+//!     units::{Duty, Rpm},
+//! };
+//!
+//! let mut detector = StallDetector::new();
+//! assert!(!detector.update(Duty::FULL, Rpm(0), 0));
+//! assert!(detector.update(Duty::FULL, Rpm(0), STALL_TIME_MS));
+//! assert!(!detector.update(Duty::FULL, Rpm(1200), STALL_TIME_MS + 100));
+//! ```
+
+use crate::{
+    units::{Duty, Rpm},
+    Error,
+};
+
+/// The duty cycle below which a fan may not turn at all, so it's never considered stalled.
+pub const MIN_SPIN_DUTY: Duty = Duty(u16::MAX / 8);
+/// The speed below which a fan driven at `MIN_SPIN_DUTY` or more is considered stuck.
+pub const STALL_RPM: Rpm = Rpm(100);
+/// How long a fan has to be stuck before it counts as stalled, which gives it time to spin up.
+pub const STALL_TIME_MS: u32 = 3000;
+
+/// Decides whether a fan has stalled from its duty cycle and measured speed over time.
+///
+/// Times are in milliseconds from any starting point, and may wrap around like a tick counter.
+#[derive(Debug, Default, Clone)]
+pub struct StallDetector {
+    stuck_since_ms: Option<u32>,
+}
+
+impl StallDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note the fan's duty cycle and speed at `now_ms`, returning whether it's stalled.
+    pub fn update(&mut self, duty: Duty, rpm: Rpm, now_ms: u32) -> bool {
+        if duty < MIN_SPIN_DUTY || rpm >= STALL_RPM {
+            self.stuck_since_ms = None;
+            return false;
+        }
+        let stuck_since_ms = *self.stuck_since_ms.get_or_insert(now_ms);
+        now_ms.wrapping_sub(stuck_since_ms) >= STALL_TIME_MS
+    }
+}
+
+/// The most points a fan curve can have, as many as fit in a `FanCurve` command.
+pub const MAX_CURVE_POINTS: usize = (crate::MAX_COMMAND_LEN - 4) / 2;
+
+/// A fan's duty cycle at a temperature.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CurvePoint {
+    pub celsius: i8,
+    /// The duty cycle, from 0 to 100%.
+    pub percent: u8,
+}
+
+/// The points of a fan curve, in order of rising temperature. Between points the duty cycle is
+/// interpolated, and outside them it's that of the nearest point.
+///
+/// With serde they're a list of points, checked like in `CurvePoints::new()`.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde_support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "Vec<CurvePoint>", try_from = "Vec<CurvePoint>")
+)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CurvePoints {
+    points: [CurvePoint; MAX_CURVE_POINTS],
+    len: u8,
+}
+
+impl CurvePoints {
+    /// Returns `None` if there are more than `MAX_CURVE_POINTS`, their temperatures don't rise,
+    /// or a duty cycle is above 100%. No points at all stop the panel regulating the fan.
+    pub fn new(points: &[CurvePoint]) -> Option<Self> {
+        let rising = points.windows(2).all(|pair| pair[0].celsius < pair[1].celsius);
+        if points.len() > MAX_CURVE_POINTS || !rising || points.iter().any(|p| p.percent > 100) {
+            return None;
+        }
+
+        let mut curve = Self { len: points.len() as u8, ..Self::default() };
+        curve.points[..points.len()].copy_from_slice(points);
+        Some(curve)
+    }
+
+    pub fn as_slice(&self) -> &[CurvePoint] {
+        &self.points[..usize::from(self.len).min(MAX_CURVE_POINTS)]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The duty cycle at a temperature in hundredths of a degree, or `None` if there are no
+    /// points.
+    pub fn duty_at(&self, centi_celsius: i16) -> Option<Duty> {
+        let points = self.as_slice();
+        let centi = |point: &CurvePoint| i32::from(point.celsius) * 100;
+        let duty = |point: &CurvePoint| {
+            i32::from(Duty::from_percent(point.percent).unwrap_or(Duty::FULL).0)
+        };
+        let temperature = i32::from(centi_celsius);
+
+        let first = points.first()?;
+        if temperature <= centi(first) {
+            return Some(Duty(duty(first) as u16));
+        }
+        for pair in points.windows(2) {
+            let (low, high) = (&pair[0], &pair[1]);
+            if temperature <= centi(high) {
+                let progress = temperature - centi(low);
+                let span = centi(high) - centi(low);
+                let value = duty(low) + (duty(high) - duty(low)) * progress / span;
+                return Some(Duty(value as u16));
+            }
+        }
+        points.last().map(|last| Duty(duty(last) as u16))
+    }
+
+    /// Parse `len` points of two bytes each, returning `Ok(None)` until all of them have
+    /// arrived.
+    pub(crate) fn try_from(len: u8, bytes: &[u8]) -> Result<Option<Self>, Error> {
+        let len = usize::from(len);
+        if len > MAX_CURVE_POINTS {
+            return Err(Error::MalformedMessage);
+        }
+        if bytes.len() < 2 * len {
+            return Ok(None);
+        }
+
+        let mut points = [CurvePoint::default(); MAX_CURVE_POINTS];
+        for (point, pair) in points.iter_mut().zip(bytes.chunks_exact(2).take(len)) {
+            *point = CurvePoint { celsius: pair[0] as i8, percent: pair[1] };
+        }
+        Self::new(&points[..len]).map(Some).ok_or(Error::MalformedMessage)
+    }
+}
+
+#[cfg(feature = "serde_support")]
+impl From<CurvePoints> for Vec<CurvePoint> {
+    fn from(curve: CurvePoints) -> Self {
+        curve.as_slice().to_vec()
+    }
+}
+
+#[cfg(feature = "serde_support")]
+impl core::convert::TryFrom<Vec<CurvePoint>> for CurvePoints {
+    type Error = &'static str;
+
+    fn try_from(points: Vec<CurvePoint>) -> Result<Self, Self::Error> {
+        Self::new(&points).ok_or(
+            "fan curve points must rise in temperature, stay at or below 100% and fit in a command",
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_stalls() {
+        let mut detector = StallDetector::new();
+        // A fan that's off or barely driven isn't expected to turn.
+        assert!(!detector.update(Duty::OFF, Rpm(0), 0));
+        assert!(!detector.update(Duty(MIN_SPIN_DUTY.0 - 1), Rpm(0), STALL_TIME_MS));
+
+        assert!(!detector.update(Duty::FULL, Rpm(50), u32::MAX - 1000));
+        assert!(!detector.update(Duty::FULL, Rpm(50), 1998));
+        assert!(detector.update(Duty::FULL, Rpm(50), 1999));
+
+        // Turning the fan down restarts the wait.
+        assert!(!detector.update(Duty::OFF, Rpm(0), 2000));
+        assert!(!detector.update(Duty::FULL, Rpm(0), 2100));
+        assert!(detector.update(Duty::FULL, Rpm(0), 5100));
+    }
+
+    #[test]
+    fn follows_curves() {
+        let point = |celsius, percent| CurvePoint { celsius, percent };
+        let curve = CurvePoints::new(&[point(30, 20), point(50, 60), point(70, 100)]).unwrap();
+        assert_eq!(curve.duty_at(-1000), Duty::from_percent(20));
+        assert_eq!(curve.duty_at(3000), Duty::from_percent(20));
+        assert_eq!(curve.duty_at(4000), Duty::from_percent(40));
+        assert_eq!(curve.duty_at(6000), Duty::from_percent(80));
+        assert_eq!(curve.duty_at(9000), Some(Duty::FULL));
+        assert_eq!(CurvePoints::default().duty_at(4000), None);
+
+        assert_eq!(CurvePoints::new(&[point(50, 20), point(30, 60)]), None);
+        assert_eq!(CurvePoints::new(&[point(30, 101)]), None);
+        assert_eq!(CurvePoints::new(&[point(30, 20); MAX_CURVE_POINTS + 1]), None);
+    }
+
+    #[cfg(feature = "serde_support")]
+    #[test]
+    fn checks_deserialized_curves() {
+        let curve: CurvePoints = serde_json::from_str(
+            r#"[{"celsius": 30, "percent": 20}, {"celsius": 70, "percent": 100}]"#,
+        )
+        .unwrap();
+        assert_eq!(curve.duty_at(9000), Some(Duty::FULL));
+        assert_eq!(
+            serde_json::to_string(&curve).unwrap(),
+            r#"[{"celsius":30,"percent":20},{"celsius":70,"percent":100}]"#
+        );
+
+        let invalid = [
+            r#"[{"celsius": 30, "percent": 101}]"#,
+            r#"[{"celsius": 50, "percent": 20}, {"celsius": 30, "percent": 60}]"#,
+            r#"{"points": [], "len": 200}"#,
+        ];
+        for json in invalid {
+            assert!(serde_json::from_str::<CurvePoints>(json).is_err(), "{}", json);
+        }
+    }
+}
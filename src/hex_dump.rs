@@ -0,0 +1,206 @@
+//! Reading bytes back from hex dumps, e.g. of a logic analyzer trace of the UART, for
+//! `panel-decode`.
+//!
+//! Three formats are understood, told apart by the layout of the first line:
+//!
+//! ```text
+//! 00000000: 4200 0064 4301 0fa0                      B..dC...
+//! 00000000  42 00 00 64 43 01 0f a0                           |B..dC...|
+//! 42 00 00 64, 0x43 0x01 0fa0
+//! ```
+//!
+//! The first is `xxd`, the second `hexdump -C`, and anything else is plain hex digits, separated
+//! by whitespace or commas and optionally prefixed with `0x`. The offset and ASCII columns of the
+//! dumps are ignored, whatever the ASCII column holds. A `*` line, which both dumps print in place
+//! of repeated lines, is expanded back into the repeated bytes.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl ParseError {
+    fn new(line_number: usize, message: impl fmt::Display) -> Self {
+        Self(format!("line {line_number}: {message}"))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Xxd,
+    Hexdump,
+    Plain,
+}
+
+impl Format {
+    fn detect(line: &str) -> Self {
+        let offset_len = line.chars().take_while(char::is_ascii_hexdigit).count();
+        let rest = &line[offset_len..];
+        if offset_len > 0 && rest.starts_with(": ") {
+            Format::Xxd
+        } else if offset_len >= 7 && rest.starts_with("  ") && line.trim_end().ends_with('|') {
+            Format::Hexdump
+        } else {
+            Format::Plain
+        }
+    }
+}
+
+/// Parse a hex dump in any of the formats above into the bytes it holds.
+pub fn parse(text: &str) -> Result<Vec<u8>, ParseError> {
+    let lines = text.lines().enumerate().map(|(index, line)| (index + 1, line));
+    let format = match text.lines().find(|line| !line.trim().is_empty()) {
+        Some(line) => Format::detect(line),
+        None => return Ok(Vec::new()),
+    };
+    if format == Format::Plain {
+        let mut bytes = Vec::new();
+        for (line_number, line) in lines {
+            for token in line.split(|c: char| c.is_whitespace() || c == ',') {
+                parse_hex(token.trim_start_matches("0x"), &mut bytes)
+                    .map_err(|e| ParseError::new(line_number, e))?;
+            }
+        }
+        return Ok(bytes);
+    }
+
+    let mut bytes = Vec::new();
+    // Where the dump started, the bytes of the last line, and whether a `*` followed them.
+    let mut start = None;
+    let mut previous: Vec<u8> = Vec::new();
+    let mut repeated = false;
+    for (line_number, line) in lines {
+        let error = |message: &str| ParseError::new(line_number, message);
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "*" {
+            if previous.is_empty() {
+                return Err(error("\"*\" without a line to repeat"));
+            }
+            repeated = true;
+            continue;
+        }
+
+        let (offset, hex) = match format {
+            Format::Xxd => line.split_once(": ").ok_or_else(|| error("missing offset"))?,
+            _ => line.split_once("  ").unwrap_or((line, "")),
+        };
+        let offset = usize::from_str_radix(offset, 16).map_err(|_| error("invalid offset"))?;
+        let start = *start.get_or_insert(offset);
+
+        // The lines a `*` stands for end where the next offset picks up.
+        if repeated {
+            while start + bytes.len() < offset {
+                bytes.extend_from_slice(&previous);
+            }
+            repeated = false;
+        }
+        if start + bytes.len() != offset {
+            return Err(error("the offset doesn't follow the previous line"));
+        }
+
+        let mut line_bytes = Vec::new();
+        match format {
+            // Groups of digits up to the two spaces before the ASCII column.
+            Format::Xxd => {
+                for token in hex.split("  ").next().unwrap_or("").split_whitespace() {
+                    parse_hex(token, &mut line_bytes).map_err(|e| error(&e))?;
+                }
+            },
+            // Single bytes up to the `|` that opens the ASCII column. `hexdump -C` ends with a
+            // line holding only the total length.
+            _ => {
+                let hex = hex.split_whitespace().take_while(|token| !token.starts_with('|'));
+                for token in hex {
+                    if token.len() != 2 {
+                        return Err(error(&format!("\"{token}\" is not a byte")));
+                    }
+                    parse_hex(token, &mut line_bytes).map_err(|e| error(&e))?;
+                }
+            },
+        }
+        bytes.extend_from_slice(&line_bytes);
+        previous = line_bytes;
+    }
+
+    if repeated {
+        return Err(ParseError("the dump ends with \"*\" and no final offset".to_string()));
+    }
+    Ok(bytes)
+}
+
+/// Append the bytes of a run of hex digits to `bytes`.
+fn parse_hex(token: &str, bytes: &mut Vec<u8>) -> Result<(), String> {
+    if !token.len().is_multiple_of(2) || !token.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("\"{token}\" is not hex"));
+    }
+    for i in (0..token.len()).step_by(2) {
+        bytes.push(u8::from_str_radix(&token[i..i + 2], 16).unwrap());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_xxd() {
+        let dump = "\
+00000000: 4200 0064 7c7c 2020 4142 4344 4546 4748  B..d||  ABCDEFGH
+00000010: 0000 0000 0000 0000 0000 0000 0000 0000  ................
+*
+00000030: 42                                       B
+";
+        let mut expected = vec![0x42, 0x00, 0x00, 0x64, b'|', b'|', b' ', b' '];
+        expected.extend_from_slice(b"ABCDEFGH");
+        expected.extend_from_slice(&[0; 32]);
+        expected.push(0x42);
+        assert_eq!(parse(dump), Ok(expected));
+    }
+
+    #[test]
+    fn parses_hexdump() {
+        let dump = "\
+00000000  42 00 00 64 7c 20 7c 41  42 43 44 45 46 47 48 49  |B..d| |ABCDEFGHI|
+00000010  ff ff ff ff ff ff ff ff  ff ff ff ff ff ff ff ff  |................|
+*
+00000040  56 01                                             |V.|
+00000042
+";
+        let mut expected = vec![0x42, 0x00, 0x00, 0x64, b'|', b' ', b'|'];
+        expected.extend_from_slice(b"ABCDEFGHI");
+        expected.extend_from_slice(&[0xff; 48]);
+        expected.extend_from_slice(&[0x56, 0x01]);
+        assert_eq!(parse(dump), Ok(expected));
+    }
+
+    #[test]
+    fn parses_plain_hex() {
+        assert_eq!(
+            parse("42 00 00 64\n0x43,0x01 0fa0\n"),
+            Ok(vec![0x42, 0, 0, 0x64, 0x43, 1, 0x0f, 0xa0])
+        );
+        assert_eq!(parse(""), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn rejects_broken_dumps() {
+        assert!(parse("42 0g").is_err());
+        assert!(parse("420").is_err());
+        // The repeated lines need an offset to end at.
+        assert!(parse("00000000  42 00  |B.|\n*\n").is_err());
+        // Lines must follow each other.
+        assert!(parse("00000000: 4200  B.\n00000010: 4200  B.\n").is_err());
+    }
+}
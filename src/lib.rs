@@ -1,45 +1,421 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use color::Rgb;
+use config::ConfigKey;
 use core::{
     convert::{TryFrom, TryInto},
     num::NonZeroU16,
 };
+use fan::CurvePoints;
+use sequence::Keyframe;
 
-pub use arrayvec::ArrayVec;
+pub use arrayvec::{ArrayString, ArrayVec};
+
+pub mod color;
+pub mod config;
+pub mod fade;
+pub mod fan;
+pub mod pulse;
+pub mod sequence;
+pub mod units;
+
+#[cfg(feature = "std")]
+pub mod capture;
+#[cfg(feature = "std")]
+pub mod discovery;
+#[cfg(feature = "std")]
+pub mod hex_dump;
+#[cfg(feature = "std")]
+pub mod loopback;
+#[cfg(feature = "std")]
+pub mod queue;
+#[cfg(feature = "std")]
+pub mod script;
+#[cfg(feature = "std")]
+pub mod self_test;
+#[cfg(feature = "std")]
+pub mod shadow;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(feature = "std")]
+pub mod telemetry;
+#[cfg(feature = "std")]
+pub mod text;
+#[cfg(feature = "tools")]
+pub mod tty;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Command {
-    Brightness { target: u8, value: u16 },
-    Temperature { target: u8, value: u16 },
-    Led { r: u8, g: u8, b: u8, pulse_mode: PulseMode },
-    FanSpeed { target: u8, value: u16 },
-    Bootload, // Restart in bootloader mode.
+    Brightness {
+        target: Target,
+        value: u16,
+    },
+    /// `value` is a color temperature in Kelvin, see `units::Kelvin`.
+    Temperature {
+        target: Target,
+        value: u16,
+    },
+    BrightnessFade {
+        target: Target,
+        value: u16,
+        transition_ms: u16,
+    },
+    TemperatureFade {
+        target: Target,
+        value: u16,
+        transition_ms: u16,
+    },
+    /// Set brightness and temperature together, so the light never shows one without the other.
+    Light {
+        target: Target,
+        brightness: u16,
+        temperature: u16,
+        transition_ms: u16,
+    },
+    Led {
+        r: u8,
+        g: u8,
+        b: u8,
+        pulse_mode: PulseMode,
+    },
+    /// `value` is a PWM duty cycle where `u16::MAX` is 100%, see `units::Duty`.
+    FanSpeed {
+        target: Target,
+        value: u16,
+    },
+    Bootload,    // Restart in bootloader mode.
+    SelfTest,    // Check every subsystem and send a SelfTestResult for each, then SelfTestDone.
+    ListTargets, // Send a TargetInfo for each setting of each light and fan, then TargetListDone.
+    /// Start uploading a sequence of `keyframe_count` keyframes to `slot`, played `loop_count`
+    /// times or forever if it's 0. See `sequence::Sequence::upload`.
+    SequenceBegin {
+        slot: u8,
+        keyframe_count: u8,
+        loop_count: u8,
+    },
+    /// Keyframes are sent in order, and the panel sends `SequenceStored` after the last one.
+    SequenceKeyframe {
+        slot: u8,
+        index: u8,
+        keyframe: Keyframe,
+    },
+    /// Play the sequence stored in `slot`, then go back to the last `Led` color and pulse mode.
+    PlaySequence {
+        slot: u8,
+    },
+    /// Set `count` pixels of the LED ring, starting at `start`, to `color`.
+    Pixels {
+        start: u8,
+        count: u8,
+        color: Rgb,
+    },
+    /// Set the pixels from `start` on in the next frame of the LED ring, which is shown all at
+    /// once by `ShowFrame`. Pixels past the end of the ring are ignored.
+    FramePixels {
+        start: u8,
+        colors: [Rgb; PIXELS_PER_COMMAND],
+    },
+    ShowFrame,
+    /// Change how `Led` colors are shown, see `color::LedConfig`.
+    LedConfig {
+        global_brightness: u8,
+        gamma: u8,
+    },
+    /// Store the current brightness and temperature of every light, speed of every fan, and LED
+    /// color and pulse mode as scene `id`. The panel replies with `SceneSaved`.
+    SaveScene {
+        id: u8,
+    },
+    /// Fade every light to scene `id` over `transition_ms`, and set the fans and LED to it.
+    RecallScene {
+        id: u8,
+        transition_ms: u16,
+    },
+    /// Change a setting stored on the panel until it restarts, see the `config` module. The
+    /// panel replies with `ConfigValue`.
+    SetConfig {
+        key: ConfigKey,
+        value: u32,
+    },
+    /// Ask for a setting, which the panel sends as `ConfigValue`.
+    GetConfig {
+        key: ConfigKey,
+    },
+    SaveConfig,   // Keep the settings changed by SetConfig across restarts.
+    FactoryReset, // Restore and save the settings the panel shipped with.
+    /// Set the LED color and pulse mode shown when the host is gone, see `config::Watchdog`. It's
+    /// kept across restarts like the other settings once `SaveConfig` is sent.
+    OfflineLed {
+        r: u8,
+        g: u8,
+        b: u8,
+        pulse_mode: PulseMode,
+    },
+    /// Tell the panel the host is still there without changing anything. Any command does, so
+    /// this only needs sending when nothing else has been for a heartbeat interval.
+    Heartbeat,
+    /// Ask for a `FanStatus` for each fan `target` addresses.
+    GetFanStatus {
+        target: Target,
+    },
+    /// Have the panel set the speed of the fans `target` addresses from the temperature of
+    /// `sensor`, until it gets a `FanSpeed` for them. No points stop it without changing the
+    /// speed. See the `fan` module.
+    FanCurve {
+        target: Target,
+        sensor: u8,
+        points: CurvePoints,
+    },
+    GetThermal, // Send a Thermal for each temperature sensor.
+}
+
+/// The lights or fans a command applies to, sent as a single byte.
+///
+/// Targets are made with `Target::single()`, `Target::mask()` or `Target::ALL`, which refuse
+/// anything the byte can't hold, so every target can be sent. `Target::address()` tells them
+/// apart.
+#[derive(PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde_support",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "Address", try_from = "Address")
+)]
+pub struct Target(u8);
+
+/// What a `Target` addresses.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Address {
+    /// One light or fan, numbered from 0 to `Target::MAX_INDEX`.
+    Single(u8),
+    /// Several of the targets numbered 0 to 6 at once, where bit n addresses target n.
+    Mask(u8),
+    /// Every light or fan on the panel.
+    All,
+}
+
+impl Target {
+    pub const ALL: Target = Target(0x80);
+    pub const BACK_LIGHTS: Target = Target(1);
+    pub const FRONT_LIGHTS: Target = Target(0);
+    pub const MAX_INDEX: u8 = 0x7f;
+    /// The targets a mask can address.
+    pub const MAX_MASK: u8 = 0x7f;
+
+    /// Returns `None` if `index` is above `Target::MAX_INDEX`.
+    pub const fn single(index: u8) -> Option<Self> {
+        if index <= Self::MAX_INDEX {
+            Some(Target(index))
+        } else {
+            None
+        }
+    }
+
+    /// Returns `None` if `mask` is empty, which would be read as `Target::ALL`, or addresses
+    /// targets above 6, which it can't on the wire.
+    pub const fn mask(mask: u8) -> Option<Self> {
+        if mask != 0 && mask <= Self::MAX_MASK {
+            Some(Target(0x80 | mask))
+        } else {
+            None
+        }
+    }
+
+    // 0x00-0x7f address a single target, and the high bit marks a mask of the low seven bits. A
+    // mask of nothing would be useless, so 0x80 addresses every target instead.
+    pub fn address(&self) -> Address {
+        match self.0 {
+            0x80 => Address::All,
+            mask if mask & 0x80 != 0 => Address::Mask(mask & Self::MAX_MASK),
+            index => Address::Single(index),
+        }
+    }
+
+    /// The targets as a bit set, where bit n is target n.
+    fn bits(&self) -> u128 {
+        match self.address() {
+            Address::Single(index) => 1 << index,
+            Address::Mask(mask) => u128::from(mask),
+            Address::All => u128::MAX,
+        }
+    }
+
+    pub fn contains(&self, index: u8) -> bool {
+        index <= Self::MAX_INDEX && self.bits() & (1 << index) != 0
+    }
+
+    /// Whether every target in `other` is also in `self`.
+    pub fn covers(&self, other: &Target) -> bool {
+        other.bits() & !self.bits() == 0
+    }
+
+    /// Whether any target is in both `self` and `other`.
+    pub fn overlaps(&self, other: &Target) -> bool {
+        self.bits() & other.bits() != 0
+    }
+}
+
+impl core::fmt::Debug for Target {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        self.address().fmt(f)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Target {
+    fn format(&self, f: defmt::Formatter) {
+        self.address().format(f)
+    }
+}
+
+impl From<Target> for Address {
+    fn from(target: Target) -> Self {
+        target.address()
+    }
+}
+
+/// Fails with `Error::InvalidTarget` for an index or mask out of range, or an empty mask.
+impl TryFrom<Address> for Target {
+    type Error = Error;
+
+    fn try_from(address: Address) -> Result<Self, Error> {
+        match address {
+            Address::Single(index) => Target::single(index),
+            Address::Mask(mask) => Target::mask(mask),
+            Address::All => Some(Target::ALL),
+        }
+        .ok_or(Error::InvalidTarget)
+    }
+}
+
+impl From<Target> for u8 {
+    fn from(target: Target) -> Self {
+        target.0
+    }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+impl From<u8> for Target {
+    fn from(byte: u8) -> Self {
+        Target(byte)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PulseMode {
     Solid,
-    Breathing { interval_ms: NonZeroU16 },
+    Breathing {
+        interval_ms: NonZeroU16,
+    },
     DialTurn,
+    Blink {
+        on_ms: NonZeroU16,
+        off_ms: NonZeroU16,
+    },
+    /// Fade from the current color to the new one, then stay solid.
+    FadeTo {
+        ms: u16,
+    },
+    /// Cycle through every hue at full saturation, ignoring the color.
+    ColorCycle {
+        period_ms: NonZeroU16,
+    },
+    /// Two quick pulses followed by a pause, like a heartbeat.
+    DoublePulse,
+    /// Light the pixel of the LED ring at the dial's position. The status LED shows the color
+    /// solid.
+    DialRing,
+}
+
+// A pulse mode is sent as a one byte tag and its parameters. The modes older panels know fit in
+// the fixed three bytes of a `D` command, and the rest are sent in a length-prefixed `L` command
+// so that panels can skip parameters and modes they don't know.
+impl PulseMode {
+    /// Whether panels that only know the `D` command can show this mode.
+    fn is_legacy(&self) -> bool {
+        matches!(self, PulseMode::Solid | PulseMode::Breathing { .. } | PulseMode::DialTurn)
+    }
+
+    pub fn as_arrayvec(&self) -> ArrayVec<u8, MAX_PULSE_MODE_LEN> {
+        let mut buf = ArrayVec::new();
+
+        match *self {
+            PulseMode::Solid => buf.push(b'S'),
+            PulseMode::DialTurn => buf.push(b'D'),
+            PulseMode::Breathing { interval_ms } => {
+                buf.push(b'B');
+                buf.try_extend_from_slice(&interval_ms.get().to_be_bytes()).unwrap();
+            },
+            PulseMode::Blink { on_ms, off_ms } => {
+                buf.push(b'K');
+                buf.try_extend_from_slice(&on_ms.get().to_be_bytes()).unwrap();
+                buf.try_extend_from_slice(&off_ms.get().to_be_bytes()).unwrap();
+            },
+            PulseMode::FadeTo { ms } => {
+                buf.push(b'F');
+                buf.try_extend_from_slice(&ms.to_be_bytes()).unwrap();
+            },
+            PulseMode::ColorCycle { period_ms } => {
+                buf.push(b'C');
+                buf.try_extend_from_slice(&period_ms.get().to_be_bytes()).unwrap();
+            },
+            PulseMode::DoublePulse => buf.push(b'H'),
+            PulseMode::DialRing => buf.push(b'R'),
+        }
+        buf
+    }
+
+    /// Parse a pulse mode from its tag and parameters in an `L` or `A` command. Parameters past
+    /// the ones a mode needs are ignored, and a mode this version doesn't know is shown as
+    /// `Solid`.
+    pub fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let non_zero = |msb, lsb| NonZeroU16::new(u16::from_be_bytes([msb, lsb]));
+
+        match *bytes {
+            [b'S', ..] => Ok(PulseMode::Solid),
+            [b'D', ..] => Ok(PulseMode::DialTurn),
+            [b'B', msb, lsb, ..] => non_zero(msb, lsb)
+                .map(|interval_ms| PulseMode::Breathing { interval_ms })
+                .ok_or(Error::MalformedMessage),
+            [b'K', on_msb, on_lsb, off_msb, off_lsb, ..] => {
+                match (non_zero(on_msb, on_lsb), non_zero(off_msb, off_lsb)) {
+                    (Some(on_ms), Some(off_ms)) => Ok(PulseMode::Blink { on_ms, off_ms }),
+                    _ => Err(Error::MalformedMessage),
+                }
+            },
+            [b'F', msb, lsb, ..] => Ok(PulseMode::FadeTo { ms: u16::from_be_bytes([msb, lsb]) }),
+            [b'C', msb, lsb, ..] => non_zero(msb, lsb)
+                .map(|period_ms| PulseMode::ColorCycle { period_ms })
+                .ok_or(Error::MalformedMessage),
+            [b'H', ..] => Ok(PulseMode::DoublePulse),
+            [b'R', ..] => Ok(PulseMode::DialRing),
+            [b'B' | b'K' | b'F' | b'C', ..] | [] => Err(Error::MalformedMessage),
+            [_, ..] => Ok(PulseMode::Solid),
+        }
+    }
 }
 
+/// The pulse mode of a `D` command, which only holds the modes older panels know. Any other mode
+/// becomes `Solid`, so it has to be sent in an `L` command instead.
 impl From<PulseMode> for [u8; 3] {
     fn from(pulse_mode: PulseMode) -> Self {
         match pulse_mode {
-            PulseMode::Solid => [b'S', 0, 0],
             PulseMode::DialTurn => [b'D', 0, 0],
             PulseMode::Breathing { interval_ms } => {
                 let interval_bytes = u16::from(interval_ms).to_be_bytes();
                 [b'B', interval_bytes[0], interval_bytes[1]]
             },
+            _ => [b'S', 0, 0],
         }
     }
 }
 
+/// Unlike in an `L` command, a mode that isn't one of the legacy ones is malformed here, as no
+/// panel sends it in a `D` command.
 impl TryFrom<[u8; 3]> for PulseMode {
     type Error = Error;
 
@@ -58,6 +434,112 @@ impl TryFrom<[u8; 3]> for PulseMode {
         }
     }
 }
+
+/// A part of the panel checked by the self-test.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Subsystem {
+    LedRed,
+    LedGreen,
+    LedBlue,
+    Light(u8),
+    Fan(u8),
+    Dial,
+    Button,
+}
+
+impl From<Subsystem> for [u8; 2] {
+    fn from(subsystem: Subsystem) -> Self {
+        match subsystem {
+            Subsystem::LedRed => [b'R', 0],
+            Subsystem::LedGreen => [b'G', 0],
+            Subsystem::LedBlue => [b'B', 0],
+            Subsystem::Light(target) => [b'L', target],
+            Subsystem::Fan(target) => [b'F', target],
+            Subsystem::Dial => [b'D', 0],
+            Subsystem::Button => [b'P', 0],
+        }
+    }
+}
+
+impl TryFrom<[u8; 2]> for Subsystem {
+    type Error = Error;
+
+    fn try_from(bytes: [u8; 2]) -> Result<Self, Error> {
+        match bytes {
+            [b'R', _] => Ok(Subsystem::LedRed),
+            [b'G', _] => Ok(Subsystem::LedGreen),
+            [b'B', _] => Ok(Subsystem::LedBlue),
+            [b'L', target] => Ok(Subsystem::Light(target)),
+            [b'F', target] => Ok(Subsystem::Fan(target)),
+            [b'D', _] => Ok(Subsystem::Dial),
+            [b'P', _] => Ok(Subsystem::Button),
+            _ => Err(Error::MalformedMessage),
+        }
+    }
+}
+
+/// The setting of a target that a `TargetInfo` describes. A light is described twice, for its
+/// brightness and its temperature, and an LED ring by the range of its pixel indices.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TargetKind {
+    Brightness,
+    Temperature,
+    FanSpeed,
+    LedRing,
+}
+
+impl From<TargetKind> for u8 {
+    fn from(kind: TargetKind) -> Self {
+        match kind {
+            TargetKind::Brightness => b'B',
+            TargetKind::Temperature => b'C',
+            TargetKind::FanSpeed => b'F',
+            TargetKind::LedRing => b'P',
+        }
+    }
+}
+
+impl TryFrom<u8> for TargetKind {
+    type Error = Error;
+
+    fn try_from(byte: u8) -> Result<Self, Error> {
+        match byte {
+            b'B' => Ok(TargetKind::Brightness),
+            b'C' => Ok(TargetKind::Temperature),
+            b'F' => Ok(TargetKind::FanSpeed),
+            b'P' => Ok(TargetKind::LedRing),
+            _ => Err(Error::MalformedMessage),
+        }
+    }
+}
+
+/// The human-readable name of a target, e.g. "Front Lights".
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+pub struct TargetName(ArrayString<MAX_TARGET_NAME_LEN>);
+
+impl TargetName {
+    /// Returns `None` if `name` is longer than `MAX_TARGET_NAME_LEN` bytes.
+    pub fn new(name: &str) -> Option<Self> {
+        ArrayString::from(name).ok().map(Self)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for TargetName {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=str}", self.as_str())
+    }
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
@@ -65,6 +547,8 @@ pub enum Error {
     MalformedMessage,
     CommandQueueFull,
     ReportQueueFull,
+    /// An `Address` that no `Target` can hold, see `Target::single()` and `Target::mask()`.
+    InvalidTarget,
 }
 
 #[cfg(feature = "std")]
@@ -81,9 +565,14 @@ impl std::error::Error for Error {}
 // cmp::max(MAX_COMMAND_LEN, MAX_REPORT_LEN)
 pub const MAX_SERIAL_MESSAGE_LEN: usize = 256;
 
-pub const MAX_COMMAND_LEN: usize = 8;
+pub const MAX_COMMAND_LEN: usize = 16;
 pub const MAX_REPORT_LEN: usize = 256;
 pub const MAX_DEBUG_MSG_LEN: usize = MAX_REPORT_LEN - 2;
+pub const MAX_TARGET_NAME_LEN: usize = 32;
+/// The number of pixels sent in each `FramePixels`.
+pub const PIXELS_PER_COMMAND: usize = 4;
+/// The longest pulse mode that fits in an `L` or `A` command after its header, color and length.
+pub const MAX_PULSE_MODE_LEN: usize = MAX_COMMAND_LEN - 5;
 
 impl Command {
     pub fn try_from(buf: &[u8]) -> Result<Option<(Command, usize)>, Error> {
@@ -95,22 +584,116 @@ impl Command {
             [] => Ok(None),
             [b'B', target, msb, lsb, ..] => {
                 let value = u16::from_be_bytes([msb, lsb]);
-                Ok(Some((Command::Brightness { target, value }, 4)))
+                Ok(Some((Command::Brightness { target: target.into(), value }, 4)))
             },
             [b'C', target, msb, lsb, ..] => {
                 let value = u16::from_be_bytes([msb, lsb]);
-                Ok(Some((Command::Temperature { target, value }, 4)))
+                Ok(Some((Command::Temperature { target: target.into(), value }, 4)))
             },
             [b'D', r, g, b, pulse_mode, pmsb, plsb, ..] => Ok(Some((
                 Command::Led { r, g, b, pulse_mode: [pulse_mode, pmsb, plsb].try_into()? },
                 7,
             ))),
+            [header @ (b'L' | b'A'), r, g, b, pulse_mode_len, ref rest @ ..] => {
+                let pulse_mode_len = pulse_mode_len as usize;
+                if pulse_mode_len > MAX_PULSE_MODE_LEN {
+                    return Err(Error::MalformedMessage);
+                }
+                if rest.len() < pulse_mode_len {
+                    return Ok(None);
+                }
+
+                let pulse_mode = PulseMode::try_from(&rest[..pulse_mode_len])?;
+                let command = match header {
+                    b'L' => Command::Led { r, g, b, pulse_mode },
+                    _ => Command::OfflineLed { r, g, b, pulse_mode },
+                };
+                Ok(Some((command, 5 + pulse_mode_len)))
+            },
             [b'E', ..] => Ok(Some((Command::Bootload, 1))),
             [b'F', target, msb, lsb, ..] => {
                 let value = u16::from_be_bytes([msb, lsb]);
-                Ok(Some((Command::FanSpeed { target, value }, 4)))
+                Ok(Some((Command::FanSpeed { target: target.into(), value }, 4)))
+            },
+            [b'G', ..] => Ok(Some((Command::SelfTest, 1))),
+            [b'K', ..] => Ok(Some((Command::ListTargets, 1))),
+            [b'H', target, msb, lsb, tmsb, tlsb, ..] => {
+                let value = u16::from_be_bytes([msb, lsb]);
+                let transition_ms = u16::from_be_bytes([tmsb, tlsb]);
+                Ok(Some((
+                    Command::BrightnessFade { target: target.into(), value, transition_ms },
+                    6,
+                )))
+            },
+            [b'I', target, msb, lsb, tmsb, tlsb, ..] => {
+                let value = u16::from_be_bytes([msb, lsb]);
+                let transition_ms = u16::from_be_bytes([tmsb, tlsb]);
+                Ok(Some((
+                    Command::TemperatureFade { target: target.into(), value, transition_ms },
+                    6,
+                )))
+            },
+            [b'J', target, bmsb, blsb, tmsb, tlsb, dmsb, dlsb, ..] => {
+                let brightness = u16::from_be_bytes([bmsb, blsb]);
+                let temperature = u16::from_be_bytes([tmsb, tlsb]);
+                let transition_ms = u16::from_be_bytes([dmsb, dlsb]);
+                Ok(Some((
+                    Command::Light {
+                        target: target.into(),
+                        brightness,
+                        temperature,
+                        transition_ms,
+                    },
+                    8,
+                )))
+            },
+            [b'M', slot, keyframe_count, loop_count, ..] => {
+                Ok(Some((Command::SequenceBegin { slot, keyframe_count, loop_count }, 4)))
+            },
+            [b'N', slot, index, r, g, b, msb, lsb, easing, ..] => {
+                let keyframe = Keyframe {
+                    color: Rgb::new(r, g, b),
+                    duration_ms: u16::from_be_bytes([msb, lsb]),
+                    easing: easing.try_into()?,
+                };
+                Ok(Some((Command::SequenceKeyframe { slot, index, keyframe }, 9)))
+            },
+            [b'O', slot, ..] => Ok(Some((Command::PlaySequence { slot }, 2))),
+            [b'P', start, count, r, g, b, ..] => {
+                Ok(Some((Command::Pixels { start, count, color: Rgb::new(r, g, b) }, 6)))
+            },
+            [b'Q', start, ref rest @ ..] if rest.len() >= 3 * PIXELS_PER_COMMAND => {
+                let mut colors = [Rgb::BLACK; PIXELS_PER_COMMAND];
+                for (color, rgb) in colors.iter_mut().zip(rest.chunks_exact(3)) {
+                    *color = Rgb::new(rgb[0], rgb[1], rgb[2]);
+                }
+                Ok(Some((Command::FramePixels { start, colors }, 2 + 3 * PIXELS_PER_COMMAND)))
+            },
+            [b'R', ..] => Ok(Some((Command::ShowFrame, 1))),
+            [b'S', global_brightness, gamma, ..] => {
+                Ok(Some((Command::LedConfig { global_brightness, gamma }, 3)))
+            },
+            [b'T', id, ..] => Ok(Some((Command::SaveScene { id }, 2))),
+            [b'U', id, msb, lsb, ..] => {
+                let transition_ms = u16::from_be_bytes([msb, lsb]);
+                Ok(Some((Command::RecallScene { id, transition_ms }, 4)))
+            },
+            [b'V', key, ref value @ ..] if value.len() >= 4 => {
+                let value = u32::from_be_bytes([value[0], value[1], value[2], value[3]]);
+                Ok(Some((Command::SetConfig { key: key.try_into()?, value }, 6)))
             },
-            [header, ..] if b"BCDF".contains(&header) => Ok(None),
+            [b'W', key, ..] => Ok(Some((Command::GetConfig { key: key.try_into()? }, 2))),
+            [b'X', ..] => Ok(Some((Command::SaveConfig, 1))),
+            [b'Y', ..] => Ok(Some((Command::FactoryReset, 1))),
+            [b'Z', ..] => Ok(Some((Command::Heartbeat, 1))),
+            [b'f', target, ..] => Ok(Some((Command::GetFanStatus { target: target.into() }, 2))),
+            [b'c', target, sensor, len, ref rest @ ..] => Ok(CurvePoints::try_from(len, rest)?
+                .map(|points| {
+                    let command = Command::FanCurve { target: target.into(), sensor, points };
+                    (command, 4 + 2 * points.as_slice().len())
+                })),
+            [b't', ..] => Ok(Some((Command::GetThermal, 1))),
+            [header, ..] if b"ABCDFHIJLMNOPQSTUVWcf".contains(&header) => Ok(None),
             _ => Err(Error::MalformedMessage),
         }
     }
@@ -121,28 +704,106 @@ impl Command {
         match *self {
             Command::Brightness { target, value } => {
                 buf.push(b'B');
-                buf.push(target);
+                buf.push(target.into());
                 buf.try_extend_from_slice(&value.to_be_bytes()).unwrap();
             },
             Command::Temperature { target, value } => {
                 buf.push(b'C');
-                buf.push(target);
+                buf.push(target.into());
                 buf.try_extend_from_slice(&value.to_be_bytes()).unwrap();
             },
-            Command::Led { r, g, b, pulse_mode } => {
+            Command::BrightnessFade { target, value, transition_ms } => {
+                buf.push(b'H');
+                buf.push(target.into());
+                buf.try_extend_from_slice(&value.to_be_bytes()).unwrap();
+                buf.try_extend_from_slice(&transition_ms.to_be_bytes()).unwrap();
+            },
+            Command::TemperatureFade { target, value, transition_ms } => {
+                buf.push(b'I');
+                buf.push(target.into());
+                buf.try_extend_from_slice(&value.to_be_bytes()).unwrap();
+                buf.try_extend_from_slice(&transition_ms.to_be_bytes()).unwrap();
+            },
+            Command::Light { target, brightness, temperature, transition_ms } => {
+                buf.push(b'J');
+                buf.push(target.into());
+                buf.try_extend_from_slice(&brightness.to_be_bytes()).unwrap();
+                buf.try_extend_from_slice(&temperature.to_be_bytes()).unwrap();
+                buf.try_extend_from_slice(&transition_ms.to_be_bytes()).unwrap();
+            },
+            Command::Led { r, g, b, pulse_mode } if pulse_mode.is_legacy() => {
                 buf.push(b'D');
-                buf.push(r);
-                buf.push(g);
-                buf.push(b);
-                let pulse_mode_bytes: [u8; 3] = pulse_mode.into();
+                buf.try_extend_from_slice(&[r, g, b]).unwrap();
+                buf.try_extend_from_slice(&<[u8; 3]>::from(pulse_mode)).unwrap();
+            },
+            Command::Led { r, g, b, pulse_mode } => {
+                let pulse_mode_bytes = pulse_mode.as_arrayvec();
+                buf.push(b'L');
+                buf.try_extend_from_slice(&[r, g, b]).unwrap();
+                buf.push(pulse_mode_bytes.len() as u8);
                 buf.try_extend_from_slice(&pulse_mode_bytes).unwrap();
             },
             Command::Bootload => buf.push(b'E'),
             Command::FanSpeed { target, value } => {
                 buf.push(b'F');
-                buf.push(target);
+                buf.push(target.into());
+                buf.try_extend_from_slice(&value.to_be_bytes()).unwrap();
+            },
+            Command::SelfTest => buf.push(b'G'),
+            Command::ListTargets => buf.push(b'K'),
+            Command::SequenceBegin { slot, keyframe_count, loop_count } => {
+                buf.try_extend_from_slice(&[b'M', slot, keyframe_count, loop_count]).unwrap();
+            },
+            Command::SequenceKeyframe { slot, index, keyframe } => {
+                let Keyframe { color, duration_ms, easing } = keyframe;
+                buf.try_extend_from_slice(&[b'N', slot, index, color.r, color.g, color.b]).unwrap();
+                buf.try_extend_from_slice(&duration_ms.to_be_bytes()).unwrap();
+                buf.push(easing.into());
+            },
+            Command::PlaySequence { slot } => buf.try_extend_from_slice(&[b'O', slot]).unwrap(),
+            Command::Pixels { start, count, color } => {
+                buf.try_extend_from_slice(&[b'P', start, count, color.r, color.g, color.b])
+                    .unwrap();
+            },
+            Command::FramePixels { start, colors } => {
+                buf.try_extend_from_slice(&[b'Q', start]).unwrap();
+                for color in &colors {
+                    buf.try_extend_from_slice(&[color.r, color.g, color.b]).unwrap();
+                }
+            },
+            Command::ShowFrame => buf.push(b'R'),
+            Command::LedConfig { global_brightness, gamma } => {
+                buf.try_extend_from_slice(&[b'S', global_brightness, gamma]).unwrap();
+            },
+            Command::SaveScene { id } => buf.try_extend_from_slice(&[b'T', id]).unwrap(),
+            Command::RecallScene { id, transition_ms } => {
+                buf.try_extend_from_slice(&[b'U', id]).unwrap();
+                buf.try_extend_from_slice(&transition_ms.to_be_bytes()).unwrap();
+            },
+            Command::SetConfig { key, value } => {
+                buf.try_extend_from_slice(&[b'V', key.into()]).unwrap();
                 buf.try_extend_from_slice(&value.to_be_bytes()).unwrap();
             },
+            Command::GetConfig { key } => buf.try_extend_from_slice(&[b'W', key.into()]).unwrap(),
+            Command::SaveConfig => buf.push(b'X'),
+            Command::FactoryReset => buf.push(b'Y'),
+            Command::OfflineLed { r, g, b, pulse_mode } => {
+                let pulse_mode_bytes = pulse_mode.as_arrayvec();
+                buf.try_extend_from_slice(&[b'A', r, g, b, pulse_mode_bytes.len() as u8]).unwrap();
+                buf.try_extend_from_slice(&pulse_mode_bytes).unwrap();
+            },
+            Command::Heartbeat => buf.push(b'Z'),
+            Command::GetFanStatus { target } => {
+                buf.try_extend_from_slice(&[b'f', target.into()]).unwrap()
+            },
+            Command::FanCurve { target, sensor, points } => {
+                let len = points.as_slice().len() as u8;
+                buf.try_extend_from_slice(&[b'c', target.into(), sensor, len]).unwrap();
+                for point in points.as_slice() {
+                    buf.try_extend_from_slice(&[point.celsius as u8, point.percent]).unwrap();
+                }
+            },
+            Command::GetThermal => buf.push(b't'),
         }
         buf
     }
@@ -153,9 +814,50 @@ impl Command {
 #[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Report {
-    DialValue { diff: i8 },
+    DialValue {
+        diff: i8,
+    },
     Press,
     Release,
+    SelfTestResult {
+        subsystem: Subsystem,
+        passed: bool,
+    },
+    SelfTestDone,
+    /// The valid range of one setting of one target, in response to `ListTargets`.
+    TargetInfo {
+        target: Target,
+        kind: TargetKind,
+        name: TargetName,
+        min: u16,
+        max: u16,
+    },
+    TargetListDone,
+    /// Every keyframe of the sequence uploaded to `slot` has arrived and been stored.
+    SequenceStored {
+        slot: u8,
+        keyframe_count: u8,
+    },
+    SceneSaved {
+        id: u8,
+    },
+    /// The value of a setting in effect, in response to `SetConfig` or `GetConfig`.
+    ConfigValue {
+        key: ConfigKey,
+        value: u32,
+    },
+    /// The measured speed of one fan and the duty cycle it's driven at, see the `fan` module.
+    FanStatus {
+        target: Target,
+        rpm: u16,
+        duty: u16,
+        stalled: bool,
+    },
+    /// The temperature of an on-board sensor, in hundredths of a degree Celsius.
+    Thermal {
+        sensor: u8,
+        centi_celsius: i16,
+    },
 }
 
 impl Report {
@@ -173,6 +875,72 @@ impl Report {
             [b'V'] => Ok(None),
             [b'P', ..] => Ok(Some((Report::Press, 1))),
             [b'R', ..] => Ok(Some((Report::Release, 1))),
+            [b'T', kind, index, passed, ..] => {
+                let passed = match passed {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(Error::MalformedMessage),
+                };
+                let subsystem = [kind, index].try_into()?;
+                Ok(Some((Report::SelfTestResult { subsystem, passed }, 4)))
+            },
+            [b'T', ..] => Ok(None),
+            [b'U', ..] => Ok(Some((Report::SelfTestDone, 1))),
+            [b'I', target, kind, min_msb, min_lsb, max_msb, max_lsb, name_len, ref rest @ ..] => {
+                let name_len = name_len as usize;
+                if name_len > MAX_TARGET_NAME_LEN {
+                    return Err(Error::MalformedMessage);
+                }
+                if rest.len() < name_len {
+                    return Ok(None);
+                }
+
+                let name = core::str::from_utf8(&rest[..name_len])
+                    .ok()
+                    .and_then(TargetName::new)
+                    .ok_or(Error::MalformedMessage)?;
+                let report = Report::TargetInfo {
+                    target: target.into(),
+                    kind: kind.try_into()?,
+                    name,
+                    min: u16::from_be_bytes([min_msb, min_lsb]),
+                    max: u16::from_be_bytes([max_msb, max_lsb]),
+                };
+                Ok(Some((report, 8 + name_len)))
+            },
+            [b'I', ..] => Ok(None),
+            [b'L', ..] => Ok(Some((Report::TargetListDone, 1))),
+            [b'S', slot, keyframe_count, ..] => {
+                Ok(Some((Report::SequenceStored { slot, keyframe_count }, 3)))
+            },
+            [b'S', ..] => Ok(None),
+            [b'W', id, ..] => Ok(Some((Report::SceneSaved { id }, 2))),
+            [b'W'] => Ok(None),
+            [b'C', key, ref value @ ..] if value.len() >= 4 => {
+                let value = u32::from_be_bytes([value[0], value[1], value[2], value[3]]);
+                Ok(Some((Report::ConfigValue { key: key.try_into()?, value }, 6)))
+            },
+            [b'C', ..] => Ok(None),
+            [b'F', target, rpm_msb, rpm_lsb, duty_msb, duty_lsb, stalled, ..] => {
+                let stalled = match stalled {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(Error::MalformedMessage),
+                };
+                let report = Report::FanStatus {
+                    target: target.into(),
+                    rpm: u16::from_be_bytes([rpm_msb, rpm_lsb]),
+                    duty: u16::from_be_bytes([duty_msb, duty_lsb]),
+                    stalled,
+                };
+                Ok(Some((report, 7)))
+            },
+            [b'F', ..] => Ok(None),
+            [b'H', sensor, msb, lsb, ..] => {
+                let centi_celsius = i16::from_be_bytes([msb, lsb]);
+                Ok(Some((Report::Thermal { sensor, centi_celsius }, 4)))
+            },
+            [b'H', ..] => Ok(None),
 
             _ => Err(Error::MalformedMessage),
         }
@@ -192,6 +960,45 @@ impl Report {
             Report::Release => {
                 buf.push(b'R');
             },
+            Report::SelfTestResult { subsystem, passed } => {
+                buf.push(b'T');
+                let subsystem_bytes: [u8; 2] = subsystem.into();
+                buf.try_extend_from_slice(&subsystem_bytes).unwrap();
+                buf.push(passed as u8);
+            },
+            Report::SelfTestDone => {
+                buf.push(b'U');
+            },
+            Report::TargetInfo { target, kind, name, min, max } => {
+                buf.push(b'I');
+                buf.push(target.into());
+                buf.push(kind.into());
+                buf.try_extend_from_slice(&min.to_be_bytes()).unwrap();
+                buf.try_extend_from_slice(&max.to_be_bytes()).unwrap();
+                buf.push(name.as_str().len() as u8);
+                buf.try_extend_from_slice(name.as_str().as_bytes()).unwrap();
+            },
+            Report::TargetListDone => {
+                buf.push(b'L');
+            },
+            Report::SequenceStored { slot, keyframe_count } => {
+                buf.try_extend_from_slice(&[b'S', slot, keyframe_count]).unwrap();
+            },
+            Report::SceneSaved { id } => buf.try_extend_from_slice(&[b'W', id]).unwrap(),
+            Report::ConfigValue { key, value } => {
+                buf.try_extend_from_slice(&[b'C', key.into()]).unwrap();
+                buf.try_extend_from_slice(&value.to_be_bytes()).unwrap();
+            },
+            Report::FanStatus { target, rpm, duty, stalled } => {
+                buf.try_extend_from_slice(&[b'F', target.into()]).unwrap();
+                buf.try_extend_from_slice(&rpm.to_be_bytes()).unwrap();
+                buf.try_extend_from_slice(&duty.to_be_bytes()).unwrap();
+                buf.push(stalled as u8);
+            },
+            Report::Thermal { sensor, centi_celsius } => {
+                buf.try_extend_from_slice(&[b'H', sensor]).unwrap();
+                buf.try_extend_from_slice(&centi_celsius.to_be_bytes()).unwrap();
+            },
         }
         buf
     }
@@ -289,9 +1096,81 @@ mod tests {
     #[test]
     fn command_roundtrips_arrayvec() {
         let commands = [
-            Command::Temperature { target: 2, value: 100 },
-            Command::Brightness { target: 10, value: 100 },
-            Command::FanSpeed { target: 1, value: 600 },
+            Command::Temperature { target: Target::single(2).unwrap(), value: 100 },
+            Command::Brightness { target: Target::single(10).unwrap(), value: 100 },
+            Command::FanSpeed { target: Target::single(1).unwrap(), value: 600 },
+            Command::FanSpeed { target: Target::mask(0b101).unwrap(), value: 600 },
+            Command::Brightness { target: Target::ALL, value: 0 },
+            Command::SelfTest,
+            Command::ListTargets,
+            Command::SequenceBegin { slot: 2, keyframe_count: 16, loop_count: 0 },
+            Command::SequenceKeyframe {
+                slot: 2,
+                index: 15,
+                keyframe: Keyframe {
+                    color: Rgb::new(255, 128, 0),
+                    duration_ms: 1500,
+                    easing: sequence::Easing::EaseInOut,
+                },
+            },
+            Command::PlaySequence { slot: 2 },
+            Command::Pixels { start: 3, count: 21, color: Rgb::new(1, 2, 3) },
+            Command::FramePixels {
+                start: 20,
+                colors: [Rgb::new(1, 2, 3), Rgb::new(4, 5, 6), Rgb::new(7, 8, 9), Rgb::BLACK],
+            },
+            Command::ShowFrame,
+            Command::Led { r: 0, g: 0, b: 255, pulse_mode: PulseMode::DialRing },
+            Command::LedConfig { global_brightness: 32, gamma: 22 },
+            Command::SaveScene { id: 1 },
+            Command::RecallScene { id: 1, transition_ms: 1500 },
+            Command::SetConfig { key: ConfigKey::DefaultLedColor, value: 0x00ff_8000 },
+            Command::GetConfig { key: ConfigKey::HeartbeatInterval },
+            Command::SaveConfig,
+            Command::FactoryReset,
+            Command::OfflineLed { r: 255, g: 0, b: 0, pulse_mode: PulseMode::Solid },
+            Command::OfflineLed {
+                r: 255,
+                g: 0,
+                b: 0,
+                pulse_mode: PulseMode::Blink {
+                    on_ms: NonZeroU16::new(250).unwrap(),
+                    off_ms: NonZeroU16::new(750).unwrap(),
+                },
+            },
+            Command::Heartbeat,
+            Command::GetFanStatus { target: Target::ALL },
+            Command::FanCurve {
+                target: Target::mask(0b11).unwrap(),
+                sensor: 1,
+                points: CurvePoints::new(&[
+                    fan::CurvePoint { celsius: -10, percent: 0 },
+                    fan::CurvePoint { celsius: 30, percent: 20 },
+                    fan::CurvePoint { celsius: 45, percent: 40 },
+                    fan::CurvePoint { celsius: 55, percent: 60 },
+                    fan::CurvePoint { celsius: 65, percent: 80 },
+                    fan::CurvePoint { celsius: 75, percent: 100 },
+                ])
+                .unwrap(),
+            },
+            Command::FanCurve { target: Target::ALL, sensor: 0, points: CurvePoints::default() },
+            Command::GetThermal,
+            Command::BrightnessFade {
+                target: Target::single(0).unwrap(),
+                value: 1200,
+                transition_ms: 500,
+            },
+            Command::TemperatureFade {
+                target: Target::single(3).unwrap(),
+                value: 4000,
+                transition_ms: 65535,
+            },
+            Command::Light {
+                target: Target::single(1).unwrap(),
+                brightness: 1200,
+                temperature: 4000,
+                transition_ms: 300,
+            },
             Command::Led { r: 0, g: 128, b: 255, pulse_mode: PulseMode::Solid },
             Command::Led { r: 0, g: 128, b: 255, pulse_mode: PulseMode::DialTurn },
             Command::Led {
@@ -300,6 +1179,23 @@ mod tests {
                 b: 255,
                 pulse_mode: PulseMode::Breathing { interval_ms: NonZeroU16::new(4000).unwrap() },
             },
+            Command::Led {
+                r: 255,
+                g: 0,
+                b: 0,
+                pulse_mode: PulseMode::Blink {
+                    on_ms: NonZeroU16::new(250).unwrap(),
+                    off_ms: NonZeroU16::new(750).unwrap(),
+                },
+            },
+            Command::Led { r: 0, g: 255, b: 0, pulse_mode: PulseMode::FadeTo { ms: 0 } },
+            Command::Led {
+                r: 0,
+                g: 0,
+                b: 0,
+                pulse_mode: PulseMode::ColorCycle { period_ms: NonZeroU16::new(6000).unwrap() },
+            },
+            Command::Led { r: 255, g: 0, b: 0, pulse_mode: PulseMode::DoublePulse },
         ];
 
         for command in commands.iter() {
@@ -319,9 +1215,116 @@ mod tests {
         }
     }
 
+    #[test]
+    fn led_encoding_is_compatible() {
+        // Modes older panels know are still sent as a `D` command.
+        let breathing = PulseMode::Breathing { interval_ms: NonZeroU16::new(4000).unwrap() };
+        assert_eq!(
+            &Command::Led { r: 1, g: 2, b: 3, pulse_mode: breathing }.as_arrayvec()[..],
+            &[b'D', 1, 2, 3, b'B', 0x0f, 0xa0]
+        );
+        assert_eq!(
+            &Command::Led { r: 1, g: 2, b: 3, pulse_mode: PulseMode::Solid }.as_arrayvec()[..],
+            &[b'D', 1, 2, 3, b'S', 0, 0]
+        );
+        assert_eq!(
+            &Command::Led { r: 1, g: 2, b: 3, pulse_mode: PulseMode::FadeTo { ms: 500 } }
+                .as_arrayvec()[..],
+            &[b'L', 1, 2, 3, 3, b'F', 0x01, 0xf4]
+        );
+
+        // Modes from newer hosts show as solid, and parameters from newer hosts are skipped.
+        let led = |pulse_mode| Command::Led { r: 1, g: 2, b: 3, pulse_mode };
+        assert_eq!(
+            Command::try_from(&[b'L', 1, 2, 3, 2, b'Z', 9, b'E']).unwrap(),
+            Some((led(PulseMode::Solid), 7))
+        );
+        assert_eq!(
+            Command::try_from(&[b'L', 1, 2, 3, 4, b'F', 0x01, 0xf4, 9]).unwrap(),
+            Some((led(PulseMode::FadeTo { ms: 500 }), 9))
+        );
+        assert!(Command::try_from(&[b'L', 1, 2, 3, 2, b'F', 0x01]).is_err());
+        assert!(Command::try_from(&[b'L', 1, 2, 3, 12]).is_err());
+
+        // A `D` command only ever holds the legacy modes, so anything else in one is malformed.
+        assert!(Command::try_from(&[b'D', 1, 2, 3, b'Z', 0, 0]).is_err());
+        assert!(Command::try_from(&[b'D', 1, 2, 3, b'K', 0x01, 0xf4]).is_err());
+        for pulse_mode in [PulseMode::Solid, PulseMode::DialTurn, breathing] {
+            assert_eq!(<[u8; 3]>::from(pulse_mode).try_into().ok(), Some(pulse_mode));
+        }
+        assert_eq!(<[u8; 3]>::from(PulseMode::DoublePulse), [b'S', 0, 0]);
+    }
+
+    #[test]
+    fn target_roundtrips_byte() {
+        for byte in 0..=u8::MAX {
+            let target = Target::from(byte);
+            assert_eq!(u8::from(target), byte);
+            assert_eq!(Target::try_from(target.address()).ok(), Some(target));
+        }
+
+        assert_eq!(Target::from(0x05).address(), Address::Single(5));
+        assert_eq!(Target::from(0x83).address(), Address::Mask(0b11));
+        assert_eq!(Target::from(0x80).address(), Address::All);
+    }
+
+    #[test]
+    fn rejects_targets_the_byte_cannot_hold() {
+        // An empty mask would reach every target, and the others some other target.
+        assert_eq!(Target::mask(0), None);
+        assert_eq!(Target::mask(0x81), None);
+        assert_eq!(Target::single(0x80), None);
+        assert!(Target::try_from(Address::Single(0xff)).is_err());
+        assert_eq!(Target::mask(0x7f).map(|target| target.address()), Some(Address::Mask(0x7f)));
+    }
+
+    #[test]
+    fn target_sets() {
+        let front_and_back = Target::mask(0b11).unwrap();
+        assert!(front_and_back.contains(1));
+        assert!(!front_and_back.contains(2));
+        assert!(front_and_back.covers(&Target::BACK_LIGHTS));
+        assert!(!Target::BACK_LIGHTS.covers(&front_and_back));
+        assert!(Target::ALL.covers(&Target::single(100).unwrap()));
+        assert!(Target::mask(0b110).unwrap().overlaps(&front_and_back));
+        assert!(!Target::single(100).unwrap().overlaps(&front_and_back));
+    }
+
     #[test]
     fn report_roundtrips_arrayvec() {
-        let reports = [Report::Press, Report::Release, Report::DialValue { diff: 100 }];
+        let reports = [
+            Report::Press,
+            Report::Release,
+            Report::DialValue { diff: 100 },
+            Report::SelfTestResult { subsystem: Subsystem::LedGreen, passed: true },
+            Report::SelfTestResult { subsystem: Subsystem::Fan(1), passed: false },
+            Report::SelfTestDone,
+            Report::TargetInfo {
+                target: Target::FRONT_LIGHTS,
+                kind: TargetKind::Temperature,
+                name: TargetName::new("Front Lights").unwrap(),
+                min: 2700,
+                max: 6500,
+            },
+            Report::TargetInfo {
+                target: Target::single(3).unwrap(),
+                kind: TargetKind::FanSpeed,
+                name: TargetName::new("").unwrap(),
+                min: 0,
+                max: 1800,
+            },
+            Report::TargetListDone,
+            Report::SequenceStored { slot: 2, keyframe_count: 16 },
+            Report::SceneSaved { id: 3 },
+            Report::ConfigValue { key: ConfigKey::PowerOnBrightness, value: 2048 },
+            Report::FanStatus {
+                target: Target::single(1).unwrap(),
+                rpm: 0,
+                duty: 65535,
+                stalled: true,
+            },
+            Report::Thermal { sensor: 0, centi_celsius: -1250 },
+        ];
 
         for report in reports.iter() {
             let serialized = report.as_arrayvec();
@@ -344,7 +1347,39 @@ mod tests {
     fn report_protocol_parse() {
         const REPORT_QUEUE_SIZE: usize = 6;
 
-        let reports = [Report::Press, Report::Release, Report::DialValue { diff: 100 }];
+        let reports = [
+            Report::Press,
+            Report::Release,
+            Report::DialValue { diff: 100 },
+            Report::SelfTestResult { subsystem: Subsystem::LedGreen, passed: true },
+            Report::SelfTestResult { subsystem: Subsystem::Fan(1), passed: false },
+            Report::SelfTestDone,
+            Report::TargetInfo {
+                target: Target::FRONT_LIGHTS,
+                kind: TargetKind::Temperature,
+                name: TargetName::new("Front Lights").unwrap(),
+                min: 2700,
+                max: 6500,
+            },
+            Report::TargetInfo {
+                target: Target::single(3).unwrap(),
+                kind: TargetKind::FanSpeed,
+                name: TargetName::new("").unwrap(),
+                min: 0,
+                max: 1800,
+            },
+            Report::TargetListDone,
+            Report::SequenceStored { slot: 2, keyframe_count: 16 },
+            Report::SceneSaved { id: 3 },
+            Report::ConfigValue { key: ConfigKey::PowerOnBrightness, value: 2048 },
+            Report::FanStatus {
+                target: Target::single(1).unwrap(),
+                rpm: 0,
+                duty: 65535,
+                stalled: true,
+            },
+            Report::Thermal { sensor: 0, centi_celsius: -1250 },
+        ];
 
         let mut protocol = ReportReader::new();
         for report_chunk in reports.chunks(REPORT_QUEUE_SIZE) {
@@ -364,8 +1399,8 @@ mod tests {
         const COMMAND_QUEUE_SIZE: usize = 6;
 
         let commands = [
-            Command::Temperature { target: 2, value: 100 },
-            Command::Brightness { target: 10, value: 100 },
+            Command::Temperature { target: Target::single(2).unwrap(), value: 100 },
+            Command::Brightness { target: Target::single(10).unwrap(), value: 100 },
             Command::Led { r: 0, g: 128, b: 255, pulse_mode: PulseMode::Solid },
             Command::Led { r: 0, g: 128, b: 255, pulse_mode: PulseMode::DialTurn },
             Command::Led {
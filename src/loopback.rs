@@ -0,0 +1,292 @@
+//! A connected pair of in-memory byte pipes, for testing host code against firmware-side
+//! logic without a serial port.
+//!
+//! Each [`Endpoint`] implements `Read` and `Write`, and behaves like the TTY the examples use:
+//! reads block until bytes arrive and fail with `io::ErrorKind::TimedOut` once the read timeout
+//! expires. [`Faults`] can be injected separately in each direction to exercise error handling.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Faults applied to the bytes travelling in one direction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Faults {
+    /// Probability (0.0 to 1.0) that a byte is lost.
+    pub drop_rate: f32,
+    /// Probability (0.0 to 1.0) that a byte arrives with one of its bits flipped.
+    pub bit_flip_rate: f32,
+    /// The most bytes a single read returns, to split messages across reads.
+    pub max_chunk_len: Option<usize>,
+    /// How long each byte takes to arrive.
+    pub delay: Duration,
+    /// Seed for the random faults, so a failing test can be reproduced.
+    pub seed: u64,
+}
+
+/// Create a connected pair of endpoints, the first for the host and the second for the panel.
+pub fn pair() -> (Endpoint, Endpoint) {
+    pair_with_faults(Faults::default(), Faults::default())
+}
+
+/// Like `pair()`, but with faults injected into the bytes going from the host to the panel and
+/// from the panel to the host, respectively.
+pub fn pair_with_faults(host_to_panel: Faults, panel_to_host: Faults) -> (Endpoint, Endpoint) {
+    let host_to_panel = Arc::new(Pipe::new(host_to_panel));
+    let panel_to_host = Arc::new(Pipe::new(panel_to_host));
+
+    let host =
+        Endpoint { tx: host_to_panel.clone(), rx: panel_to_host.clone(), read_timeout: None };
+    let panel = Endpoint { tx: panel_to_host, rx: host_to_panel, read_timeout: None };
+    (host, panel)
+}
+
+pub struct Endpoint {
+    tx: Arc<Pipe>,
+    rx: Arc<Pipe>,
+    read_timeout: Option<Duration>,
+}
+
+impl Endpoint {
+    /// Set how long a read waits for bytes before failing with `io::ErrorKind::TimedOut`.
+    /// `None` waits forever.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+}
+
+impl Read for Endpoint {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = self.read_timeout.map(|timeout| Instant::now() + timeout);
+        self.rx.read(buf, deadline)
+    }
+}
+
+impl Write for Endpoint {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tx.write(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Endpoint {
+    fn drop(&mut self) {
+        self.tx.close();
+        self.rx.close();
+    }
+}
+
+struct PipeState {
+    // Each byte along with the instant it becomes readable.
+    bytes: VecDeque<(Instant, u8)>,
+    closed: bool,
+    rng: Rng,
+}
+
+struct Pipe {
+    faults: Faults,
+    state: Mutex<PipeState>,
+    ready: Condvar,
+}
+
+impl Pipe {
+    fn new(faults: Faults) -> Self {
+        let state = PipeState { bytes: VecDeque::new(), closed: false, rng: Rng::new(faults.seed) };
+        Self { faults, state: Mutex::new(state), ready: Condvar::new() }
+    }
+
+    fn write(&self, buf: &[u8]) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+
+        let arrival = Instant::now() + self.faults.delay;
+        for &byte in buf {
+            if state.rng.chance(self.faults.drop_rate) {
+                continue;
+            }
+            let byte = if state.rng.chance(self.faults.bit_flip_rate) {
+                byte ^ (1 << state.rng.below(8))
+            } else {
+                byte
+            };
+            state.bytes.push_back((arrival, byte));
+        }
+
+        self.ready.notify_all();
+        Ok(())
+    }
+
+    fn read(&self, buf: &mut [u8], deadline: Option<Instant>) -> io::Result<usize> {
+        let max_len = self.faults.max_chunk_len.map_or(buf.len(), |len| len.min(buf.len()));
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            let now = Instant::now();
+            let available = state.bytes.iter().take_while(|(arrival, _)| *arrival <= now).count();
+            if available > 0 {
+                let len = available.min(max_len);
+                for (dest, (_, byte)) in buf.iter_mut().zip(state.bytes.drain(..len)) {
+                    *dest = byte;
+                }
+                return Ok(len);
+            }
+            if state.closed && state.bytes.is_empty() {
+                return Ok(0);
+            }
+
+            // Wake up when the next byte arrives, the deadline passes, or the writer notifies.
+            let next_arrival = state.bytes.front().map(|(arrival, _)| *arrival);
+            let wake_at = match (next_arrival, deadline) {
+                (Some(arrival), Some(deadline)) => Some(arrival.min(deadline)),
+                (arrival, deadline) => arrival.or(deadline),
+            };
+            if deadline.is_some_and(|deadline| deadline <= now) {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+
+            state = match wake_at {
+                Some(wake_at) => {
+                    self.ready
+                        .wait_timeout(state, wake_at.saturating_duration_since(now))
+                        .unwrap()
+                        .0
+                },
+                None => self.ready.wait(state).unwrap(),
+            };
+        }
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.ready.notify_all();
+    }
+}
+
+/// A small xorshift generator, good enough for picking which bytes to corrupt.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at zero.
+        Self(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn chance(&mut self, probability: f32) -> bool {
+        // The top 24 bits give a uniform value in 0.0..1.0 at full f32 precision.
+        let sample = (self.next() >> 40) as f32 / (1u64 << 24) as f32;
+        sample < probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Command, CommandReader, PulseMode, Report, ReportReader, Target, MAX_SERIAL_MESSAGE_LEN,
+    };
+    use std::thread;
+
+    const QUEUE_SIZE: usize = 8;
+
+    #[test]
+    fn commands_reach_the_panel() {
+        let (mut host, mut panel) = pair();
+        let commands = [
+            Command::Brightness { target: Target::single(0).unwrap(), value: 1000 },
+            Command::Led { r: 255, g: 0, b: 0, pulse_mode: PulseMode::DialTurn },
+            Command::Bootload,
+        ];
+
+        for command in &commands {
+            host.write_all(&command.as_arrayvec()).unwrap();
+        }
+
+        let mut reader = CommandReader::new();
+        let mut received = Vec::new();
+        let mut buf = [0u8; MAX_SERIAL_MESSAGE_LEN];
+        while received.len() < commands.len() {
+            let count = panel.read(&mut buf).unwrap();
+            received.extend(reader.process_bytes::<QUEUE_SIZE>(&buf[..count]).unwrap());
+        }
+
+        assert_eq!(received, commands);
+    }
+
+    #[test]
+    fn fragmented_and_delayed_reports_reach_the_host() {
+        let faults = Faults {
+            max_chunk_len: Some(1),
+            delay: Duration::from_millis(5),
+            ..Default::default()
+        };
+        let (mut host, mut panel) = pair_with_faults(Faults::default(), faults);
+        let reports = [Report::Press, Report::DialValue { diff: -3 }, Report::Release];
+
+        let writer = thread::spawn(move || {
+            for report in &reports {
+                panel.write_all(&report.as_arrayvec()).unwrap();
+            }
+        });
+
+        let mut reader = ReportReader::new();
+        let mut received = Vec::new();
+        let mut buf = [0u8; MAX_SERIAL_MESSAGE_LEN];
+        loop {
+            match host.read(&mut buf).unwrap() {
+                0 => break,
+                count => {
+                    assert_eq!(count, 1);
+                    received.extend(reader.process_bytes::<QUEUE_SIZE>(&buf[..count]).unwrap());
+                },
+            }
+        }
+        writer.join().unwrap();
+
+        assert_eq!(received, reports);
+    }
+
+    #[test]
+    fn reads_time_out() {
+        let faults = Faults { drop_rate: 1.0, ..Default::default() };
+        let (mut host, mut panel) = pair_with_faults(faults, Faults::default());
+        host.write_all(&Command::Bootload.as_arrayvec()).unwrap();
+
+        panel.set_read_timeout(Some(Duration::from_millis(10)));
+        let err = panel.read(&mut [0u8; 8]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn bit_flips_are_detected() {
+        let faults = Faults { bit_flip_rate: 1.0, seed: 7, ..Default::default() };
+        let (mut host, mut panel) = pair_with_faults(faults, Faults::default());
+        let command = Command::Brightness { target: Target::single(0).unwrap(), value: 1000 };
+        host.write_all(&command.as_arrayvec()).unwrap();
+        drop(host);
+
+        let mut bytes = Vec::new();
+        panel.read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), command.as_arrayvec().len());
+        assert!(!matches!(Command::try_from(&bytes), Ok(Some((parsed, _))) if parsed == command));
+    }
+}
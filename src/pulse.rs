@@ -0,0 +1,184 @@
+//! The color the status LED shows over time in each `PulseMode`, so the firmware and host
+//! previews animate it identically.
+//!
+//! ```
+//! use core::num::NonZeroU16;
+//! use panel_protocol::{color::Rgb, pulse::{render, Context}, PulseMode};
+//!
+//! let breathing = PulseMode::Breathing { interval_ms: NonZeroU16::new(1000).unwrap() };
+//! let red = Rgb::new(255, 0, 0);
+//! assert_eq!(render(red, breathing, 0, Context::default()), red);
+//! assert_eq!(render(red, breathing, 500, Context::default()), Rgb::new(127, 0, 0));
+//! assert_eq!(render(red, breathing, 1000, Context::default()), Rgb::BLACK);
+//! ```
+
+use crate::{
+    color::{Hsv, Rgb},
+    fade::Fade,
+    PulseMode,
+};
+
+/// How long the LED takes to fade out after the dial stops turning in `DialTurn` mode.
+pub const DIAL_TURN_FADE_MS: u32 = 1000;
+
+const DOUBLE_PULSE_PERIOD_MS: u32 = 1200;
+const DOUBLE_PULSE_ON_MS: u32 = 120;
+
+/// What a pulse mode needs to know besides the time since it was set.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Context {
+    /// The color the LED showed when the mode was set, which `FadeTo` starts from.
+    pub previous: Rgb,
+    /// How long ago the dial last turned, or `None` if it never has.
+    pub since_dial_turn_ms: Option<u32>,
+    /// The sum of every `DialValue` diff so far.
+    pub dial_position: i32,
+}
+
+/// The color to show `elapsed_ms` after a `Command::Led` set `color` and `pulse_mode`.
+pub fn render(color: Rgb, pulse_mode: PulseMode, elapsed_ms: u32, context: Context) -> Rgb {
+    match pulse_mode {
+        PulseMode::Solid | PulseMode::DialRing => color,
+        // Fade out over one interval and back in over the next.
+        PulseMode::Breathing { interval_ms } => {
+            let interval_ms = u32::from(interval_ms.get());
+            let phase = elapsed_ms % (2 * interval_ms);
+            let since_dark = interval_ms.abs_diff(phase);
+            color.scaled(Fade::new(0, 255, interval_ms).value_at(since_dark) as u8)
+        },
+        PulseMode::DialTurn => match context.since_dial_turn_ms {
+            Some(since_ms) => {
+                color.scaled(Fade::new(255, 0, DIAL_TURN_FADE_MS).value_at(since_ms) as u8)
+            },
+            None => Rgb::BLACK,
+        },
+        PulseMode::Blink { on_ms, off_ms } => {
+            let on_ms = u32::from(on_ms.get());
+            if elapsed_ms % (on_ms + u32::from(off_ms.get())) < on_ms {
+                color
+            } else {
+                Rgb::BLACK
+            }
+        },
+        PulseMode::FadeTo { ms } => {
+            let channel = |from: u8, to: u8| {
+                Fade::new(from.into(), to.into(), ms.into()).value_at(elapsed_ms) as u8
+            };
+            Rgb::new(
+                channel(context.previous.r, color.r),
+                channel(context.previous.g, color.g),
+                channel(context.previous.b, color.b),
+            )
+        },
+        PulseMode::ColorCycle { period_ms } => {
+            let period_ms = u32::from(period_ms.get());
+            let hue = (elapsed_ms % period_ms) * 360 / period_ms;
+            Rgb::from(Hsv { hue: hue as u16, saturation: 255, value: 255 })
+        },
+        PulseMode::DoublePulse => {
+            let phase = elapsed_ms % DOUBLE_PULSE_PERIOD_MS;
+            let second_pulse = 2 * DOUBLE_PULSE_ON_MS..3 * DOUBLE_PULSE_ON_MS;
+            if phase < DOUBLE_PULSE_ON_MS || second_pulse.contains(&phase) {
+                color
+            } else {
+                Rgb::BLACK
+            }
+        },
+    }
+}
+
+/// The color of pixel `index` of an LED ring with `pixel_count` pixels. Every pixel shows the
+/// same as the status LED, except in `DialRing` mode.
+pub fn render_pixel(
+    color: Rgb,
+    pulse_mode: PulseMode,
+    elapsed_ms: u32,
+    context: Context,
+    index: u16,
+    pixel_count: u16,
+) -> Rgb {
+    match pulse_mode {
+        PulseMode::DialRing if pixel_count == 0 => Rgb::BLACK,
+        PulseMode::DialRing => {
+            if context.dial_position.rem_euclid(pixel_count.into()) == i32::from(index) {
+                color
+            } else {
+                Rgb::BLACK
+            }
+        },
+        _ => render(color, pulse_mode, elapsed_ms, context),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::num::NonZeroU16;
+
+    const RED: Rgb = Rgb::new(255, 0, 0);
+
+    fn ms(ms: u16) -> NonZeroU16 {
+        NonZeroU16::new(ms).unwrap()
+    }
+
+    #[test]
+    fn breathes() {
+        let breathing = PulseMode::Breathing { interval_ms: ms(1000) };
+        let levels = [0, 1, 2, 3, 4, 5, 6, 7, 8]
+            .map(|step| render(RED, breathing, step * 250, Context::default()).r);
+        assert_eq!(levels, [255, 191, 127, 63, 0, 63, 127, 191, 255]);
+    }
+
+    #[test]
+    fn blinks_and_pulses() {
+        let blink = PulseMode::Blink { on_ms: ms(100), off_ms: ms(300) };
+        let lit =
+            |pulse_mode, elapsed_ms| render(RED, pulse_mode, elapsed_ms, Context::default()) == RED;
+
+        assert!(lit(blink, 0));
+        assert!(lit(blink, 99));
+        assert!(!lit(blink, 100));
+        assert!(!lit(blink, 399));
+        assert!(lit(blink, 400));
+
+        let pulses = [0, 120, 240, 360, 1199, 1200].map(|t| lit(PulseMode::DoublePulse, t));
+        assert_eq!(pulses, [true, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn fades_from_the_previous_color() {
+        let context = Context { previous: Rgb::new(0, 0, 200), ..Context::default() };
+        let fade = PulseMode::FadeTo { ms: 1000 };
+        assert_eq!(render(RED, fade, 0, context), Rgb::new(0, 0, 200));
+        assert_eq!(render(RED, fade, 500, context), Rgb::new(127, 0, 100));
+        assert_eq!(render(RED, fade, 2000, context), RED);
+    }
+
+    #[test]
+    fn follows_the_dial_and_cycles_colors() {
+        let after_turn = |since_dial_turn_ms| Context { since_dial_turn_ms, ..Context::default() };
+        assert_eq!(render(RED, PulseMode::DialTurn, 0, after_turn(None)), Rgb::BLACK);
+        assert_eq!(render(RED, PulseMode::DialTurn, 0, after_turn(Some(0))), RED);
+        assert_eq!(render(RED, PulseMode::DialTurn, 0, after_turn(Some(500))), Rgb::new(128, 0, 0));
+
+        let cycle = PulseMode::ColorCycle { period_ms: ms(3600) };
+        assert_eq!(render(RED, cycle, 0, Context::default()), RED);
+        assert_eq!(render(Rgb::BLACK, cycle, 1200, Context::default()), Rgb::new(0, 255, 0));
+        assert_eq!(render(Rgb::BLACK, cycle, 2400, Context::default()), Rgb::new(0, 0, 255));
+    }
+
+    #[test]
+    fn shows_the_dial_position_on_the_ring() {
+        let ring = |dial_position| {
+            let context = Context { dial_position, ..Context::default() };
+            [0, 1, 2, 3]
+                .map(|index| render_pixel(RED, PulseMode::DialRing, 0, context, index, 4) == RED)
+        };
+        assert_eq!(ring(0), [true, false, false, false]);
+        assert_eq!(ring(6), [false, false, true, false]);
+        assert_eq!(ring(-1), [false, false, false, true]);
+
+        let breathing = PulseMode::Breathing { interval_ms: ms(1000) };
+        assert_eq!(render_pixel(RED, breathing, 1000, Context::default(), 3, 4), Rgb::BLACK);
+    }
+}
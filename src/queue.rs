@@ -0,0 +1,654 @@
+//! A host-side queue that sits between the application and the serial link.
+//!
+//! Commands that supersede each other (e.g. a new `Brightness` for the same target) are
+//! coalesced so only the latest value goes out, commands are paced to a maximum rate, and
+//! urgent commands are sent ahead of bulk updates unless they'd change state a queued bulk
+//! command uses, e.g. an `Led` queued after a `SaveScene`.
+
+use crate::{config::ConfigKey, Command, Target};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Continuous updates like brightness or fan speed, where only the latest value matters.
+    Bulk,
+    /// Commands the user expects to take effect immediately.
+    Urgent,
+}
+
+impl Priority {
+    /// Every command is listed, without a fallback, so a new one has to be given a priority.
+    pub fn of(command: &Command) -> Self {
+        match command {
+            // Sequences are urgent like `Led`, so uploads and plays stay in order with it.
+            Command::Led { .. }
+            | Command::Bootload
+            | Command::SelfTest
+            | Command::ListTargets
+            | Command::SequenceBegin { .. }
+            | Command::SequenceKeyframe { .. }
+            | Command::PlaySequence { .. }
+            | Command::SetConfig { .. }
+            | Command::GetConfig { .. }
+            | Command::SaveConfig
+            | Command::FactoryReset
+            | Command::OfflineLed { .. }
+            | Command::GetFanStatus { .. }
+            | Command::GetThermal => Priority::Urgent,
+            Command::Brightness { .. }
+            | Command::Temperature { .. }
+            | Command::BrightnessFade { .. }
+            | Command::TemperatureFade { .. }
+            | Command::Light { .. }
+            | Command::FanSpeed { .. }
+            | Command::Pixels { .. }
+            | Command::FramePixels { .. }
+            | Command::ShowFrame
+            | Command::LedConfig { .. }
+            | Command::SaveScene { .. }
+            | Command::RecallScene { .. }
+            | Command::Heartbeat
+            | Command::FanCurve { .. } => Priority::Bulk,
+        }
+    }
+}
+
+/// Identifies which piece of panel state a command overwrites.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Key {
+    Brightness(Target),
+    Temperature(Target),
+    Light(Target),
+    Led,
+    FanSpeed(Target),
+    Bootload,
+    SelfTest,
+    ListTargets,
+    SequenceBegin(u8),
+    SequenceKeyframe(u8, u8),
+    PlaySequence(u8),
+    Pixels(u8, u8),
+    FramePixels(u8),
+    ShowFrame,
+    LedConfig,
+    SaveScene(u8),
+    RecallScene(u8),
+    Config(ConfigKey),
+    GetConfig(ConfigKey),
+    SaveConfig,
+    FactoryReset,
+    OfflineLed,
+    Heartbeat,
+    GetFanStatus(Target),
+    FanCurve(Target),
+    GetThermal,
+}
+
+const BRIGHTNESS: u8 = 1 << 0;
+const TEMPERATURE: u8 = 1 << 1;
+const FAN_SPEED: u8 = 1 << 2;
+
+impl Key {
+    /// The per-target settings the key writes, as a set of flags, and the targets it writes them
+    /// to.
+    fn settings(&self) -> Option<(u8, Target)> {
+        match *self {
+            Key::Brightness(target) => Some((BRIGHTNESS, target)),
+            Key::Temperature(target) => Some((TEMPERATURE, target)),
+            Key::Light(target) => Some((BRIGHTNESS | TEMPERATURE, target)),
+            // A curve decides the fan speed from then on, and a manual speed ends the curve.
+            Key::FanSpeed(target) | Key::FanCurve(target) => Some((FAN_SPEED, target)),
+            Key::Led
+            | Key::Bootload
+            | Key::SelfTest
+            | Key::ListTargets
+            | Key::SequenceBegin(_)
+            | Key::SequenceKeyframe(..)
+            | Key::PlaySequence(_)
+            | Key::Pixels(..)
+            | Key::FramePixels(_)
+            | Key::ShowFrame
+            | Key::LedConfig
+            | Key::SaveScene(_)
+            | Key::RecallScene(_)
+            | Key::Config(_)
+            | Key::GetConfig(_)
+            | Key::SaveConfig
+            | Key::FactoryReset
+            | Key::OfflineLed
+            | Key::Heartbeat
+            | Key::GetFanStatus(_)
+            | Key::GetThermal => None,
+        }
+    }
+
+    /// Whether a command with this key overwrites everything a command with `other` does.
+    fn supersedes(&self, other: &Key) -> bool {
+        match (self.settings(), other.settings()) {
+            // A curve without points leaves the fan speed as it was.
+            (Some(_), Some(_)) if matches!(self, Key::FanCurve(_)) => false,
+            (Some((settings, target)), Some((other_settings, other_target))) => {
+                other_settings & !settings == 0 && target.covers(&other_target)
+            },
+            // An upload or frame only takes effect once every part has arrived, and a scene
+            // depends on what was sent before it, so none of these is ever dropped.
+            _ if matches!(
+                self,
+                Key::SequenceBegin(_)
+                    | Key::SequenceKeyframe(..)
+                    | Key::FramePixels(_)
+                    | Key::SaveScene(_)
+                    | Key::RecallScene(_)
+            ) =>
+            {
+                false
+            },
+            _ => self == other,
+        }
+    }
+
+    /// Whether a command with this key uses the state written by a command with `other` sent
+    /// before it.
+    fn reads(&self, other: &Key) -> bool {
+        match *self {
+            Key::SaveScene(_) => other.is_scene_state() || other.is_scene(),
+            Key::GetConfig(key) => *other == Key::Config(key),
+            Key::SaveConfig => matches!(other, Key::Config(_) | Key::OfflineLed),
+            _ => false,
+        }
+    }
+
+    fn is_scene(&self) -> bool {
+        matches!(self, Key::SaveScene(_) | Key::RecallScene(_))
+    }
+
+    /// Whether the key writes state that scenes save and recall.
+    fn is_scene_state(&self) -> bool {
+        self.settings().is_some() || *self == Key::Led
+    }
+
+    /// Whether commands with this key and `other` write any of the same state.
+    fn conflicts(&self, other: &Key) -> bool {
+        // Saving or recalling a scene reads or writes every light and fan, and the LED.
+        if self.is_scene() && (other.is_scene() || other.is_scene_state())
+            || other.is_scene() && self.is_scene_state()
+        {
+            return true;
+        }
+
+        match (self.settings(), other.settings()) {
+            (Some((settings, target)), Some((other_settings, other_target))) => {
+                settings & other_settings != 0 && target.overlaps(&other_target)
+            },
+            // Playing a sequence and setting the LED both change what it shows, and a sequence
+            // plays whatever was uploaded to its slot before it. Showing a frame sets every
+            // pixel of the ring, and shows the frame pixels queued before it.
+            (None, None) => match (*self, *other) {
+                (Key::Led, Key::PlaySequence(_)) | (Key::PlaySequence(_), Key::Led) => true,
+                (
+                    Key::PlaySequence(slot),
+                    Key::SequenceBegin(other) | Key::SequenceKeyframe(other, _),
+                )
+                | (
+                    Key::SequenceBegin(slot) | Key::SequenceKeyframe(slot, _),
+                    Key::PlaySequence(other),
+                ) => slot == other,
+                (Key::Pixels(start, count), Key::Pixels(other_start, other_count)) => {
+                    let end = u16::from(start) + u16::from(count);
+                    let other_end = u16::from(other_start) + u16::from(other_count);
+                    u16::from(start) < other_end && u16::from(other_start) < end
+                },
+                (Key::Pixels(..) | Key::FramePixels(_), Key::ShowFrame)
+                | (Key::ShowFrame, Key::Pixels(..) | Key::FramePixels(_)) => true,
+                // Reading a setting sees the value set before it, and saving or resetting the
+                // settings covers every one of them.
+                (
+                    Key::Config(key) | Key::GetConfig(key),
+                    Key::Config(other) | Key::GetConfig(other),
+                ) => key == other,
+                (Key::SaveConfig | Key::FactoryReset, other)
+                | (other, Key::SaveConfig | Key::FactoryReset) => {
+                    matches!(
+                        other,
+                        Key::Config(_)
+                            | Key::GetConfig(_)
+                            | Key::SaveConfig
+                            | Key::FactoryReset
+                            | Key::OfflineLed
+                    )
+                },
+                _ => self == other,
+            },
+            _ => false,
+        }
+    }
+}
+
+impl From<&Command> for Key {
+    fn from(command: &Command) -> Self {
+        match *command {
+            Command::Brightness { target, .. } | Command::BrightnessFade { target, .. } => {
+                Key::Brightness(target)
+            },
+            Command::Temperature { target, .. } | Command::TemperatureFade { target, .. } => {
+                Key::Temperature(target)
+            },
+            Command::Light { target, .. } => Key::Light(target),
+            Command::Led { .. } => Key::Led,
+            Command::FanSpeed { target, .. } => Key::FanSpeed(target),
+            Command::Bootload => Key::Bootload,
+            Command::SelfTest => Key::SelfTest,
+            Command::ListTargets => Key::ListTargets,
+            Command::SequenceBegin { slot, .. } => Key::SequenceBegin(slot),
+            Command::SequenceKeyframe { slot, index, .. } => Key::SequenceKeyframe(slot, index),
+            Command::PlaySequence { slot } => Key::PlaySequence(slot),
+            Command::Pixels { start, count, .. } => Key::Pixels(start, count),
+            Command::FramePixels { start, .. } => Key::FramePixels(start),
+            Command::ShowFrame => Key::ShowFrame,
+            Command::LedConfig { .. } => Key::LedConfig,
+            Command::SaveScene { id } => Key::SaveScene(id),
+            Command::RecallScene { id, .. } => Key::RecallScene(id),
+            Command::SetConfig { key, .. } => Key::Config(key),
+            Command::GetConfig { key } => Key::GetConfig(key),
+            Command::SaveConfig => Key::SaveConfig,
+            Command::FactoryReset => Key::FactoryReset,
+            Command::OfflineLed { .. } => Key::OfflineLed,
+            Command::Heartbeat => Key::Heartbeat,
+            Command::GetFanStatus { target } => Key::GetFanStatus(target),
+            Command::FanCurve { target, .. } => Key::FanCurve(target),
+            Command::GetThermal => Key::GetThermal,
+        }
+    }
+}
+
+pub struct CommandQueue {
+    min_interval: Duration,
+    last_sent: Option<Instant>,
+    urgent: VecDeque<Command>,
+    bulk: VecDeque<Command>,
+}
+
+impl CommandQueue {
+    /// Create a queue which releases at most one command per `min_interval`.
+    pub fn new(min_interval: Duration) -> Self {
+        Self { min_interval, last_sent: None, urgent: VecDeque::new(), bulk: VecDeque::new() }
+    }
+
+    /// Queue a command, replacing any pending command it supersedes.
+    pub fn push(&mut self, command: Command) {
+        // An urgent command that changes state a pending bulk command uses waits behind it, so
+        // e.g. a `SaveScene` stores the `Led` sent before it and not one sent after.
+        let key = Key::from(&command);
+        let waits = self.bulk.iter().any(|pending| Key::from(pending).conflicts(&key));
+        let queue = match Priority::of(&command) {
+            Priority::Urgent if waits => &mut self.bulk,
+            Priority::Urgent => &mut self.urgent,
+            Priority::Bulk => &mut self.bulk,
+        };
+
+        // Replace a pending command for the same state in place to keep its turn, unless a
+        // command queued after it writes some of that state too and would then override the
+        // newer value, e.g. a `Light` after a `Brightness` for the same target.
+        if let Some(index) = queue.iter().position(|pending| Key::from(pending) == key) {
+            if !queue.iter().skip(index + 1).any(|pending| Key::from(pending).conflicts(&key)) {
+                queue[index] = command;
+                return;
+            }
+        }
+
+        // Drop the pending commands the new one overwrites, unless a command kept after them
+        // still reads their state, e.g. a `SaveScene` storing it.
+        let mut kept_after: Vec<Key> = Vec::new();
+        for index in (0..queue.len()).rev() {
+            let pending = Key::from(&queue[index]);
+            if key.supersedes(&pending) && !kept_after.iter().any(|later| later.reads(&pending)) {
+                queue.remove(index);
+            } else {
+                kept_after.push(pending);
+            }
+        }
+        queue.push_back(command);
+    }
+
+    /// Take the next command to send, if the rate limit allows sending one at `now`.
+    pub fn pop(&mut self, now: Instant) -> Option<Command> {
+        if self.last_sent.is_some_and(|last_sent| now < last_sent + self.min_interval) {
+            return None;
+        }
+
+        let command = self.urgent.pop_front().or_else(|| self.bulk.pop_front())?;
+        self.last_sent = Some(now);
+        Some(command)
+    }
+
+    /// The earliest instant at which `pop()` can return a command, or `None` if the queue is
+    /// empty.
+    pub fn next_ready(&self) -> Option<Instant> {
+        if self.is_empty() {
+            return None;
+        }
+
+        Some(match self.last_sent {
+            Some(last_sent) => last_sent + self.min_interval,
+            None => Instant::now(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.urgent.len() + self.bulk.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urgent.is_empty() && self.bulk.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.urgent.clear();
+        self.bulk.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        color::Rgb,
+        fan::{CurvePoint, CurvePoints},
+        sequence::{Easing, Keyframe},
+        PulseMode,
+    };
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn coalesces_superseded_commands() {
+        let mut queue = CommandQueue::new(INTERVAL);
+        queue.push(Command::Brightness { target: Target::single(0).unwrap(), value: 1 });
+        queue.push(Command::Brightness { target: Target::single(1).unwrap(), value: 2 });
+        queue.push(Command::Temperature { target: Target::single(0).unwrap(), value: 3 });
+        queue.push(Command::Brightness { target: Target::single(0).unwrap(), value: 4 });
+
+        let start = Instant::now();
+        let sent: Vec<_> = (0..4).filter_map(|i| queue.pop(start + INTERVAL * i)).collect();
+
+        assert_eq!(
+            sent,
+            [
+                Command::Brightness { target: Target::single(0).unwrap(), value: 4 },
+                Command::Brightness { target: Target::single(1).unwrap(), value: 2 },
+                Command::Temperature { target: Target::single(0).unwrap(), value: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn light_supersedes_brightness_and_temperature() {
+        let mut queue = CommandQueue::new(INTERVAL);
+        let light = Command::Light {
+            target: Target::single(0).unwrap(),
+            brightness: 5,
+            temperature: 6,
+            transition_ms: 0,
+        };
+        queue.push(Command::Brightness { target: Target::single(0).unwrap(), value: 1 });
+        queue.push(Command::Temperature { target: Target::single(1).unwrap(), value: 2 });
+        queue.push(Command::TemperatureFade {
+            target: Target::single(0).unwrap(),
+            value: 3,
+            transition_ms: 100,
+        });
+        queue.push(light);
+        queue.push(Command::Brightness { target: Target::single(0).unwrap(), value: 7 });
+
+        let start = Instant::now();
+        let sent: Vec<_> = (0..4).filter_map(|i| queue.pop(start + INTERVAL * i)).collect();
+
+        assert_eq!(
+            sent,
+            [
+                Command::Temperature { target: Target::single(1).unwrap(), value: 2 },
+                light,
+                Command::Brightness { target: Target::single(0).unwrap(), value: 7 },
+            ]
+        );
+    }
+
+    #[test]
+    fn broadcasts_supersede_the_targets_they_cover() {
+        let mut queue = CommandQueue::new(INTERVAL);
+        queue.push(Command::Brightness { target: Target::single(0).unwrap(), value: 1 });
+        queue.push(Command::Brightness { target: Target::single(5).unwrap(), value: 2 });
+        queue.push(Command::Brightness { target: Target::mask(0b11).unwrap(), value: 3 });
+        queue.push(Command::Brightness { target: Target::single(0).unwrap(), value: 4 });
+        queue.push(Command::FanSpeed { target: Target::ALL, value: 5 });
+        queue.push(Command::Brightness { target: Target::ALL, value: 6 });
+        queue.push(Command::Brightness { target: Target::single(1).unwrap(), value: 7 });
+
+        let start = Instant::now();
+        let sent: Vec<_> = (0..4).filter_map(|i| queue.pop(start + INTERVAL * i)).collect();
+
+        assert_eq!(
+            sent,
+            [
+                Command::FanSpeed { target: Target::ALL, value: 5 },
+                Command::Brightness { target: Target::ALL, value: 6 },
+                Command::Brightness { target: Target::single(1).unwrap(), value: 7 },
+            ]
+        );
+    }
+
+    #[test]
+    fn urgent_commands_jump_the_queue() {
+        let mut queue = CommandQueue::new(INTERVAL);
+        queue.push(Command::FanSpeed { target: Target::single(0).unwrap(), value: 600 });
+        queue.push(Command::Led { r: 255, g: 0, b: 0, pulse_mode: PulseMode::Solid });
+        queue.push(Command::Bootload);
+
+        let start = Instant::now();
+        assert_eq!(
+            queue.pop(start),
+            Some(Command::Led { r: 255, g: 0, b: 0, pulse_mode: PulseMode::Solid })
+        );
+        assert_eq!(queue.pop(start + INTERVAL), Some(Command::Bootload));
+        assert_eq!(
+            queue.pop(start + INTERVAL * 2),
+            Some(Command::FanSpeed { target: Target::single(0).unwrap(), value: 600 })
+        );
+    }
+
+    #[test]
+    fn sequences_stay_in_order_with_the_led() {
+        let mut queue = CommandQueue::new(INTERVAL);
+        let led = |r| Command::Led { r, g: 0, b: 0, pulse_mode: PulseMode::Solid };
+        let keyframe = |r| Command::SequenceKeyframe {
+            slot: 1,
+            index: 0,
+            keyframe: Keyframe {
+                color: Rgb::new(r, 0, 0),
+                duration_ms: 100,
+                easing: Easing::Linear,
+            },
+        };
+        queue.push(led(1));
+        queue.push(Command::SequenceBegin { slot: 1, keyframe_count: 1, loop_count: 1 });
+        queue.push(keyframe(2));
+        queue.push(keyframe(3));
+        queue.push(Command::PlaySequence { slot: 1 });
+        queue.push(keyframe(4));
+        queue.push(led(5));
+
+        let start = Instant::now();
+        let sent: Vec<_> = (0..6).filter_map(|i| queue.pop(start + INTERVAL * i)).collect();
+
+        assert_eq!(
+            sent,
+            [
+                Command::SequenceBegin { slot: 1, keyframe_count: 1, loop_count: 1 },
+                keyframe(3),
+                Command::PlaySequence { slot: 1 },
+                keyframe(4),
+                led(5),
+            ]
+        );
+    }
+
+    #[test]
+    fn frames_are_shown_whole() {
+        let mut queue = CommandQueue::new(INTERVAL);
+        let frame = |start, r| Command::FramePixels { start, colors: [Rgb::new(r, 0, 0); 4] };
+        let pixels = |start, count, r| Command::Pixels { start, count, color: Rgb::new(r, 0, 0) };
+        queue.push(pixels(0, 1, 1));
+        queue.push(pixels(0, 24, 2));
+        queue.push(pixels(0, 1, 3));
+        queue.push(frame(0, 4));
+        queue.push(frame(4, 5));
+        queue.push(Command::ShowFrame);
+        queue.push(frame(0, 6));
+        queue.push(Command::ShowFrame);
+
+        let start = Instant::now();
+        let sent: Vec<_> = (0..7).filter_map(|i| queue.pop(start + INTERVAL * i)).collect();
+
+        assert_eq!(
+            sent,
+            [
+                pixels(0, 24, 2),
+                pixels(0, 1, 3),
+                frame(0, 4),
+                frame(4, 5),
+                frame(0, 6),
+                Command::ShowFrame
+            ]
+        );
+    }
+
+    #[test]
+    fn scenes_see_the_settings_sent_before_them() {
+        let mut queue = CommandQueue::new(INTERVAL);
+        let brightness = |value| Command::Brightness { target: Target::single(0).unwrap(), value };
+        queue.push(brightness(1));
+        queue.push(Command::SaveScene { id: 1 });
+        queue.push(brightness(2));
+        queue.push(Command::RecallScene { id: 1, transition_ms: 0 });
+        queue.push(Command::SaveScene { id: 1 });
+        queue.push(brightness(3));
+
+        let start = Instant::now();
+        let sent: Vec<_> = (0..6).filter_map(|i| queue.pop(start + INTERVAL * i)).collect();
+
+        assert_eq!(
+            sent,
+            [
+                brightness(1),
+                Command::SaveScene { id: 1 },
+                brightness(2),
+                Command::RecallScene { id: 1, transition_ms: 0 },
+                Command::SaveScene { id: 1 },
+                brightness(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn scenes_keep_their_place_with_the_led() {
+        let mut queue = CommandQueue::new(INTERVAL);
+        let led = |color: Rgb| Command::Led {
+            r: color.r,
+            g: color.g,
+            b: color.b,
+            pulse_mode: PulseMode::Solid,
+        };
+        queue.push(led(Rgb::new(255, 0, 0)));
+        queue.push(Command::SaveScene { id: 1 });
+        queue.push(led(Rgb::new(0, 255, 0)));
+        queue.push(led(Rgb::new(0, 0, 255)));
+        queue.push(Command::RecallScene { id: 1, transition_ms: 0 });
+        queue.push(led(Rgb::WHITE));
+        queue.push(Command::ListTargets);
+
+        let start = Instant::now();
+        let sent: Vec<_> = (0..5).filter_map(|i| queue.pop(start + INTERVAL * i)).collect();
+
+        // The colors set between the scenes are dropped, as the recall replaces them anyway.
+        assert_eq!(
+            sent,
+            [
+                led(Rgb::new(255, 0, 0)),
+                Command::ListTargets,
+                Command::SaveScene { id: 1 },
+                Command::RecallScene { id: 1, transition_ms: 0 },
+                led(Rgb::WHITE),
+            ]
+        );
+
+        // Without a scene pending, the LED goes ahead of bulk updates again.
+        queue.push(Command::Brightness { target: Target::single(0).unwrap(), value: 1 });
+        queue.push(led(Rgb::BLACK));
+        assert_eq!(queue.pop(start + INTERVAL * 5), Some(led(Rgb::BLACK)));
+    }
+
+    #[test]
+    fn config_is_read_and_saved_as_set() {
+        let mut queue = CommandQueue::new(INTERVAL);
+        let sensitivity = |value| Command::SetConfig { key: ConfigKey::DialSensitivity, value };
+        let heartbeat = |value| Command::SetConfig { key: ConfigKey::HeartbeatInterval, value };
+        queue.push(sensitivity(2));
+        queue.push(Command::GetConfig { key: ConfigKey::DialSensitivity });
+        queue.push(sensitivity(3));
+        queue.push(heartbeat(100));
+        queue.push(Command::SaveConfig);
+        queue.push(heartbeat(200));
+        queue.push(heartbeat(300));
+
+        let start = Instant::now();
+        let sent: Vec<_> = (0..7).filter_map(|i| queue.pop(start + INTERVAL * i)).collect();
+
+        assert_eq!(
+            sent,
+            [
+                sensitivity(2),
+                Command::GetConfig { key: ConfigKey::DialSensitivity },
+                sensitivity(3),
+                heartbeat(100),
+                Command::SaveConfig,
+                heartbeat(300),
+            ]
+        );
+    }
+
+    #[test]
+    fn manual_fan_speeds_end_curves() {
+        let mut queue = CommandQueue::new(INTERVAL);
+        let speed = |value| Command::FanSpeed { target: Target::single(0).unwrap(), value };
+        let curve = |points| Command::FanCurve { target: Target::ALL, sensor: 0, points };
+        let points = CurvePoints::new(&[CurvePoint { celsius: 40, percent: 50 }]).unwrap();
+        queue.push(speed(1));
+        queue.push(curve(points));
+        queue.push(speed(2));
+        queue.push(curve(CurvePoints::default()));
+
+        let start = Instant::now();
+        let sent: Vec<_> = (0..4).filter_map(|i| queue.pop(start + INTERVAL * i)).collect();
+
+        // The curve still applies to the other fans.
+        assert_eq!(sent, [curve(points), speed(2), curve(CurvePoints::default())]);
+    }
+
+    #[test]
+    fn enforces_rate_limit() {
+        let mut queue = CommandQueue::new(INTERVAL);
+        queue.push(Command::Brightness { target: Target::single(0).unwrap(), value: 1 });
+        queue.push(Command::Brightness { target: Target::single(1).unwrap(), value: 1 });
+
+        let start = Instant::now();
+        assert!(queue.pop(start).is_some());
+        assert_eq!(queue.next_ready(), Some(start + INTERVAL));
+        assert!(queue.pop(start + INTERVAL / 2).is_none());
+        assert!(queue.pop(start + INTERVAL).is_some());
+        assert!(queue.is_empty());
+        assert_eq!(queue.next_ready(), None);
+    }
+}
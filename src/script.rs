@@ -0,0 +1,371 @@
+//! Timed sequences of commands, for factory tests and demo choreography.
+//!
+//! A script has one step per line, and lines starting with `#` are comments:
+//!
+//! ```text
+//! # Fade the front lights up once the LED turns red.
+//! at 0ms led red solid
+//! at 500ms brightness 0 0 -> 4000 over 2s
+//! wait for press within 30s
+//! at 0ms led off
+//! ```
+//!
+//! `at` sends a command (in the syntax of the [`text`](crate::text) module) at a time measured
+//! from the start of the script, or from the end of the most recent `wait`. Brightness,
+//! temperature and fan speed can ramp from one value to another over a duration. `wait` blocks
+//! until the panel reports a `press`, `release` or `dial` turn, ignoring reports that arrived
+//! before it began, and fails if `within` is given and runs out.
+
+use crate::{
+    capture::Message,
+    fade::Fade,
+    stream::ReportStream,
+    text::{self, ParseError},
+    Command, Report,
+};
+use std::{
+    convert::TryInto,
+    fmt,
+    io::{self, Read, Write},
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often ramps send an updated value.
+pub const RAMP_STEP_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long a `wait` without `within` lasts, i.e. for practical purposes forever.
+const NO_TIMEOUT: Duration = Duration::from_secs(60 * 60 * 24 * 365);
+
+/// The commands whose value can ramp.
+const RAMPS: [&str; 3] = ["brightness", "temperature", "fan"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Press,
+    Release,
+    DialTurn,
+}
+
+impl Event {
+    pub fn matches(&self, report: &Report) -> bool {
+        matches!(
+            (self, report),
+            (Event::Press, Report::Press)
+                | (Event::Release, Report::Release)
+                | (Event::DialTurn, Report::DialValue { .. })
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Step {
+    /// Send a command.
+    Send { at: Duration, command: Command },
+    /// Send a series of commands moving from the value of `from` to the value of `to`.
+    Ramp { at: Duration, from: Command, to: Command, duration: Duration },
+    /// Wait for the panel to report an event.
+    Wait { event: Event, timeout: Option<Duration> },
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(io::Error),
+    TimedOut(Event),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScriptError::Io(e) => write!(f, "{e}"),
+            ScriptError::TimedOut(event) => write!(f, "timed out waiting for {event:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+impl From<io::Error> for ScriptError {
+    fn from(e: io::Error) -> Self {
+        ScriptError::Io(e)
+    }
+}
+
+/// The brightness, temperature or fan speed command `elapsed` into a ramp from the value of
+/// `from` to the value of `to`.
+fn interpolate(from: &Command, to: &Command, elapsed: Duration, duration: Duration) -> Command {
+    let elapsed_ms = elapsed.as_millis().try_into().unwrap_or(u32::MAX);
+    let duration_ms = duration.as_millis().try_into().unwrap_or(u32::MAX);
+    let lerp = |from, to| Fade::new(from, to, duration_ms).value_at(elapsed_ms);
+
+    match (*from, *to) {
+        (Command::Brightness { target, value: from }, Command::Brightness { value: to, .. }) => {
+            Command::Brightness { target, value: lerp(from, to) }
+        },
+        (Command::Temperature { target, value: from }, Command::Temperature { value: to, .. }) => {
+            Command::Temperature { target, value: lerp(from, to) }
+        },
+        (Command::FanSpeed { target, value: from }, Command::FanSpeed { value: to, .. }) => {
+            Command::FanSpeed { target, value: lerp(from, to) }
+        },
+        _ => *to,
+    }
+}
+
+/// Parse a duration like `500ms`, `2s` or `1.5s`.
+pub fn parse_duration(word: Option<&str>) -> Result<Duration, ParseError> {
+    let word = word.ok_or_else(|| ParseError::new("missing duration"))?;
+    let invalid = || ParseError::new(format!("invalid duration \"{word}\""));
+
+    if let Some(ms) = word.strip_suffix("ms") {
+        ms.parse().map(Duration::from_millis).map_err(|_| invalid())
+    } else if let Some(secs) = word.strip_suffix('s') {
+        secs.parse()
+            .ok()
+            .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+            .ok_or_else(invalid)
+    } else {
+        Err(invalid())
+    }
+}
+
+fn parse_step(words: &[&str]) -> Result<Step, ParseError> {
+    match words {
+        ["at", at, command @ ..] => {
+            let at = parse_duration(Some(at))?;
+            match command {
+                [name, target, from, "->", to, "over", duration] if RAMPS.contains(name) => {
+                    Ok(Step::Ramp {
+                        at,
+                        from: text::parse_command(&[name, target, from])?,
+                        to: text::parse_command(&[name, target, to])?,
+                        duration: parse_duration(Some(duration))?,
+                    })
+                },
+                _ => Ok(Step::Send { at, command: text::parse_command(command)? }),
+            }
+        },
+        ["wait", event @ ..] => {
+            let event = match event {
+                ["for", event @ ..] => event,
+                event => event,
+            };
+            let (event, timeout) = match event {
+                [event] => (event, None),
+                [event, "within", timeout] => (event, Some(parse_duration(Some(timeout))?)),
+                _ => return Err(ParseError::new("expected \"wait <event> [within <duration>]\"")),
+            };
+            let event = match *event {
+                "press" => Event::Press,
+                "release" => Event::Release,
+                "dial" => Event::DialTurn,
+                event => return Err(ParseError::new(format!("unknown event \"{event}\""))),
+            };
+            Ok(Step::Wait { event, timeout })
+        },
+        _ => Err(ParseError::new("expected a line starting with \"at\" or \"wait\"")),
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Script {
+    pub steps: Vec<Step>,
+}
+
+impl FromStr for Script {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, ParseError> {
+        let mut steps = Vec::new();
+        for (line_number, line) in text.lines().enumerate() {
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+            let words: Vec<_> = line.split_whitespace().collect();
+            if words.is_empty() {
+                continue;
+            }
+
+            let step = parse_step(&words)
+                .map_err(|e| ParseError::new(format!("line {}: {e}", line_number + 1)))?;
+            steps.push(step);
+        }
+
+        Ok(Script { steps })
+    }
+}
+
+impl Script {
+    /// Run the script against a panel. `observer` sees every command sent and report received.
+    pub fn run<T: Read + Write>(
+        &self,
+        transport: &mut T,
+        mut observer: impl FnMut(Message),
+    ) -> Result<(), ScriptError> {
+        let mut epoch = Instant::now();
+
+        for step in &self.steps {
+            match *step {
+                Step::Send { at, command } => {
+                    sleep_until(epoch + at);
+                    send(transport, &mut observer, command)?;
+                },
+                Step::Ramp { at, from, to, duration } => {
+                    sleep_until(epoch + at);
+                    let start = Instant::now();
+                    loop {
+                        let elapsed = start.elapsed();
+                        if elapsed >= duration {
+                            send(transport, &mut observer, to)?;
+                            break;
+                        }
+                        let command = interpolate(&from, &to, elapsed, duration);
+                        send(transport, &mut observer, command)?;
+                        thread::sleep(RAMP_STEP_INTERVAL);
+                    }
+                },
+                Step::Wait { event, timeout } => {
+                    let deadline = Instant::now() + timeout.unwrap_or(NO_TIMEOUT);
+                    let mut reports = ReportStream::new(&mut *transport);
+                    // A press or dial turn from before the wait, e.g. during a ramp, doesn't end it.
+                    reports.discard_pending(deadline)?;
+                    loop {
+                        let report = reports.next(deadline)?.ok_or(ScriptError::TimedOut(event))?;
+                        observer(Message::Report(report));
+                        if event.matches(&report) {
+                            break;
+                        }
+                    }
+                    epoch = Instant::now();
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn send<T: Write>(
+    transport: &mut T,
+    observer: &mut impl FnMut(Message),
+    command: Command,
+) -> io::Result<()> {
+    transport.write_all(&command.as_arrayvec())?;
+    observer(Message::Command(command));
+    Ok(())
+}
+
+fn sleep_until(instant: Instant) {
+    thread::sleep(instant.saturating_duration_since(Instant::now()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{loopback, CommandReader, PulseMode, Target, MAX_SERIAL_MESSAGE_LEN};
+
+    #[test]
+    fn parses_script() {
+        let script: Script = "
+            # A comment.
+            at 0ms led red solid
+            at 500ms brightness 0 0 -> 4000 over 2s
+            wait for press within 30s
+            at 1.5s fan 1 600
+        "
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            script.steps,
+            [
+                Step::Send {
+                    at: Duration::ZERO,
+                    command: Command::Led { r: 255, g: 0, b: 0, pulse_mode: PulseMode::Solid },
+                },
+                Step::Ramp {
+                    at: Duration::from_millis(500),
+                    from: Command::Brightness { target: Target::single(0).unwrap(), value: 0 },
+                    to: Command::Brightness { target: Target::single(0).unwrap(), value: 4000 },
+                    duration: Duration::from_secs(2),
+                },
+                Step::Wait { event: Event::Press, timeout: Some(Duration::from_secs(30)) },
+                Step::Send {
+                    at: Duration::from_millis(1500),
+                    command: Command::FanSpeed { target: Target::single(1).unwrap(), value: 600 },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_line_of_invalid_step() {
+        let err = "at 0ms bootload\nwait for applause".parse::<Script>().unwrap_err();
+        assert_eq!(err.to_string(), "line 2: unknown event \"applause\"");
+    }
+
+    #[test]
+    fn runs_against_panel() {
+        let script: Script =
+            "at 0ms brightness 0 0 -> 100 over 50ms\nwait press\nat 0ms bootload".parse().unwrap();
+        let (mut host, mut panel) = loopback::pair();
+        host.set_read_timeout(Some(Duration::from_millis(10)));
+
+        let panel = thread::spawn(move || {
+            // Pressed during the ramp, so it mustn't end the wait.
+            panel.write_all(&Report::Press.as_arrayvec()).unwrap();
+
+            let mut reader = CommandReader::new();
+            let mut commands = Vec::new();
+            let mut buf = [0u8; MAX_SERIAL_MESSAGE_LEN];
+            let mut pressed = false;
+            while commands.last() != Some(&Command::Bootload) {
+                let count = panel.read(&mut buf).unwrap();
+                commands.extend(reader.process_bytes::<16>(&buf[..count]).unwrap());
+
+                let ramped = Command::Brightness { target: Target::single(0).unwrap(), value: 100 };
+                if !pressed && commands.contains(&ramped) {
+                    // Give the host time to start waiting.
+                    thread::sleep(Duration::from_millis(50));
+                    panel.write_all(&Report::DialValue { diff: 1 }.as_arrayvec()).unwrap();
+                    panel.write_all(&Report::Press.as_arrayvec()).unwrap();
+                    pressed = true;
+                }
+            }
+            commands
+        });
+
+        let mut messages = Vec::new();
+        script.run(&mut host, |message| messages.push(message)).unwrap();
+        let commands = panel.join().unwrap();
+
+        assert_eq!(
+            commands.first(),
+            Some(&Command::Brightness { target: Target::single(0).unwrap(), value: 0 })
+        );
+        assert!(commands
+            .contains(&Command::Brightness { target: Target::single(0).unwrap(), value: 100 }));
+        assert!(commands.windows(2).all(|pair| match pair {
+            [Command::Brightness { value: a, .. }, Command::Brightness { value: b, .. }] => a <= b,
+            _ => true,
+        }));
+        let reports: Vec<_> =
+            messages.iter().filter(|message| matches!(message, Message::Report(_))).collect();
+        assert_eq!(
+            reports,
+            [&Message::Report(Report::DialValue { diff: 1 }), &Message::Report(Report::Press)]
+        );
+        assert_eq!(messages.last(), Some(&Message::Command(Command::Bootload)));
+    }
+
+    #[test]
+    fn wait_times_out() {
+        let script: Script = "wait dial within 20ms".parse().unwrap();
+        let (mut host, _panel) = loopback::pair();
+        host.set_read_timeout(Some(Duration::from_millis(5)));
+
+        let result = script.run(&mut host, |_| {});
+        assert!(matches!(result, Err(ScriptError::TimedOut(Event::DialTurn))));
+    }
+}
@@ -0,0 +1,236 @@
+//! A host-side runner for the factory end-of-line test.
+//!
+//! The panel checks what it can by itself when it receives `Command::SelfTest`, reporting a
+//! `SelfTestResult` per subsystem and then `SelfTestDone`. The dial and button also need a
+//! person to move them, so the runner then prompts the operator to turn the dial and press the
+//! button, and checks that the panel reports it. Reports that arrived before each prompt don't
+//! count, so a dial bumped or a button pressed too early can't pass a check.
+
+use crate::{stream::ReportStream, Command, Report, Subsystem};
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+/// The outcome of one check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Check {
+    pub name: String,
+    pub passed: bool,
+}
+
+/// The outcome of a whole test run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TestReport {
+    pub checks: Vec<Check>,
+}
+
+impl TestReport {
+    /// Whether every check passed. A run with no checks at all didn't pass.
+    pub fn passed(&self) -> bool {
+        !self.checks.is_empty() && self.checks.iter().all(|check| check.passed)
+    }
+}
+
+impl fmt::Display for TestReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{}  {}", if check.passed { "PASS" } else { "FAIL" }, check.name)?;
+        }
+        write!(f, "Result: {}", if self.passed() { "PASS" } else { "FAIL" })
+    }
+}
+
+fn describe(subsystem: Subsystem) -> String {
+    match subsystem {
+        Subsystem::LedRed => "LED red channel".to_string(),
+        Subsystem::LedGreen => "LED green channel".to_string(),
+        Subsystem::LedBlue => "LED blue channel".to_string(),
+        Subsystem::Light(target) => format!("light {target}"),
+        Subsystem::Fan(target) => format!("fan {target}"),
+        Subsystem::Dial => "dial encoder".to_string(),
+        Subsystem::Button => "button".to_string(),
+    }
+}
+
+pub struct SelfTest {
+    /// How long the panel gets to finish checking itself.
+    pub panel_timeout: Duration,
+    /// How long the operator gets for each step.
+    pub operator_timeout: Duration,
+}
+
+impl Default for SelfTest {
+    fn default() -> Self {
+        Self { panel_timeout: Duration::from_secs(5), operator_timeout: Duration::from_secs(30) }
+    }
+}
+
+impl SelfTest {
+    /// Run the test against a panel, calling `prompt` with instructions for the operator.
+    ///
+    /// Failed checks are part of the returned report; an error means the test couldn't be run,
+    /// e.g. because the panel never finished its part.
+    pub fn run<T: Read + Write>(
+        &self,
+        transport: &mut T,
+        mut prompt: impl FnMut(&str),
+    ) -> io::Result<TestReport> {
+        transport.write_all(&Command::SelfTest.as_arrayvec())?;
+
+        let mut report = TestReport::default();
+        let mut reports = ReportStream::new(transport);
+
+        let deadline = Instant::now() + self.panel_timeout;
+        loop {
+            match reports.next(deadline)? {
+                Some(Report::SelfTestResult { subsystem, passed }) => {
+                    report.checks.push(Check { name: describe(subsystem), passed })
+                },
+                Some(Report::SelfTestDone) => break,
+                Some(_) => {},
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "the panel did not finish its self-test",
+                    ))
+                },
+            }
+        }
+
+        reports.discard_pending(Instant::now() + self.panel_timeout)?;
+        prompt("Turn the dial");
+        let deadline = Instant::now() + self.operator_timeout;
+        let passed =
+            reports.wait_for(deadline, |report| matches!(report, Report::DialValue { .. }))?;
+        report.checks.push(Check { name: "dial turned by operator".to_string(), passed });
+
+        reports.discard_pending(Instant::now() + self.panel_timeout)?;
+        prompt("Press and release the button");
+        let deadline = Instant::now() + self.operator_timeout;
+        let passed = reports.wait_for(deadline, |report| *report == Report::Press)?
+            && reports.wait_for(deadline, |report| *report == Report::Release)?;
+        report.checks.push(Check { name: "button pressed by operator".to_string(), passed });
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loopback;
+
+    fn send(panel: &mut impl Write, reports: &[Report]) {
+        for report in reports {
+            panel.write_all(&report.as_arrayvec()).unwrap();
+        }
+    }
+
+    #[test]
+    fn walks_through_test() {
+        let (mut host, mut panel) = loopback::pair();
+        host.set_read_timeout(Some(Duration::from_millis(10)));
+        send(
+            &mut panel,
+            &[
+                Report::SelfTestResult { subsystem: Subsystem::LedRed, passed: true },
+                Report::SelfTestResult { subsystem: Subsystem::Fan(1), passed: false },
+                Report::SelfTestDone,
+            ],
+        );
+
+        // The operator does as they're told once prompted.
+        let mut prompts = Vec::new();
+        let report = SelfTest::default()
+            .run(&mut host, |prompt| {
+                prompts.push(prompt.to_string());
+                match prompts.len() {
+                    1 => send(&mut panel, &[Report::DialValue { diff: 1 }]),
+                    _ => send(&mut panel, &[Report::Press, Report::Release]),
+                }
+            })
+            .unwrap();
+
+        let mut buf = [0u8; 1];
+        panel.read_exact(&mut buf).unwrap();
+        assert_eq!(Command::try_from(&buf).unwrap(), Some((Command::SelfTest, 1)));
+        assert_eq!(prompts, ["Turn the dial", "Press and release the button"]);
+        assert_eq!(
+            report
+                .checks
+                .iter()
+                .map(|check| (check.name.as_str(), check.passed))
+                .collect::<Vec<_>>(),
+            [
+                ("LED red channel", true),
+                ("fan 1", false),
+                ("dial turned by operator", true),
+                ("button pressed by operator", true),
+            ]
+        );
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn fails_steps_the_operator_skips() {
+        let (mut host, mut panel) = loopback::pair();
+        host.set_read_timeout(Some(Duration::from_millis(5)));
+        send(&mut panel, &[Report::SelfTestDone, Report::Press]);
+
+        let self_test = SelfTest {
+            panel_timeout: Duration::from_millis(100),
+            operator_timeout: Duration::from_millis(20),
+        };
+        let report = self_test.run(&mut host, |_| {}).unwrap();
+
+        assert_eq!(report.checks.len(), 2);
+        assert!(report.checks.iter().all(|check| !check.passed));
+    }
+
+    #[test]
+    fn ignores_actions_before_prompts() {
+        let (mut host, mut panel) = loopback::pair();
+        host.set_read_timeout(Some(Duration::from_millis(5)));
+        // The dial is bumped and the button pressed before the operator is asked to.
+        send(
+            &mut panel,
+            &[Report::SelfTestDone, Report::DialValue { diff: 1 }, Report::Press, Report::Release],
+        );
+
+        let self_test = SelfTest {
+            panel_timeout: Duration::from_millis(100),
+            operator_timeout: Duration::from_millis(20),
+        };
+        let mut prompts = 0;
+        let report = self_test
+            .run(&mut host, |_| {
+                prompts += 1;
+                if prompts == 1 {
+                    // Turning the dial as asked, but pressing the button before the next prompt.
+                    send(
+                        &mut panel,
+                        &[Report::DialValue { diff: -1 }, Report::Press, Report::Release],
+                    );
+                }
+            })
+            .unwrap();
+
+        assert_eq!(
+            report.checks.iter().map(|check| check.passed).collect::<Vec<_>>(),
+            [true, false]
+        );
+    }
+
+    #[test]
+    fn times_out_without_panel_results() {
+        let (mut host, _panel) = loopback::pair();
+        host.set_read_timeout(Some(Duration::from_millis(5)));
+
+        let self_test =
+            SelfTest { panel_timeout: Duration::from_millis(20), ..SelfTest::default() };
+        let err = self_test.run(&mut host, |_| {}).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
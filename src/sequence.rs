@@ -0,0 +1,277 @@
+//! Keyframe animations for the status LED, uploaded to a numbered slot on the panel and played
+//! later by number.
+//!
+//! A sequence is too long for one command, so it's uploaded as a `SequenceBegin` followed by a
+//! `SequenceKeyframe` for each keyframe, in order. The panel confirms it has stored the whole
+//! sequence with a `SequenceStored` report.
+//!
+//! ```
+//! use panel_protocol::{
+//!     color::Rgb,
+//!     sequence::{Easing, Keyframe, Sequence},
+//!     Command,
+//! };
+//!
+//! let mut alarm = Sequence::new(3);
+//! alarm.push(Keyframe { color: Rgb::new(255, 0, 0), duration_ms: 200, easing: Easing::EaseOut });
+//! alarm.push(Keyframe { color: Rgb::BLACK, duration_ms: 300, easing: Easing::EaseIn });
+//!
+//! let commands: Vec<Command> = alarm.upload(2).collect();
+//! assert_eq!(commands.len(), 3);
+//! assert_eq!(alarm.color_at(200), Some(Rgb::new(255, 0, 0)));
+//! assert_eq!(alarm.color_at(1500), None);
+//! ```
+
+use crate::{color::Rgb, fade::Fade, ArrayVec, Command, Error};
+use core::convert::TryFrom;
+
+/// The most keyframes a sequence can have.
+pub const MAX_KEYFRAMES: usize = 16;
+
+/// How a keyframe moves from the previous color to its own.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Hold the previous color, then jump at the end of the keyframe.
+    Step,
+}
+
+impl Easing {
+    /// Map linear progress through a keyframe to eased progress, both out of `u16::MAX`.
+    pub fn ease(&self, progress: u16) -> u16 {
+        let max = u64::from(u16::MAX);
+        let p = u64::from(progress);
+        let eased = match self {
+            Easing::Linear => p,
+            Easing::EaseIn => p * p / max,
+            Easing::EaseOut => max - (max - p) * (max - p) / max,
+            // Smoothstep, 3p² - 2p³.
+            Easing::EaseInOut => p * p * (3 * max - 2 * p) / (max * max),
+            Easing::Step if progress == u16::MAX => max,
+            Easing::Step => 0,
+        };
+        eased as u16
+    }
+}
+
+impl From<Easing> for u8 {
+    fn from(easing: Easing) -> Self {
+        match easing {
+            Easing::Linear => b'L',
+            Easing::EaseIn => b'I',
+            Easing::EaseOut => b'O',
+            Easing::EaseInOut => b'B',
+            Easing::Step => b'S',
+        }
+    }
+}
+
+impl TryFrom<u8> for Easing {
+    type Error = Error;
+
+    fn try_from(byte: u8) -> Result<Self, Error> {
+        match byte {
+            b'L' => Ok(Easing::Linear),
+            b'I' => Ok(Easing::EaseIn),
+            b'O' => Ok(Easing::EaseOut),
+            b'B' => Ok(Easing::EaseInOut),
+            b'S' => Ok(Easing::Step),
+            _ => Err(Error::MalformedMessage),
+        }
+    }
+}
+
+/// A step of a sequence, which reaches `color` `duration_ms` after the previous keyframe.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Keyframe {
+    pub color: Rgb,
+    pub duration_ms: u16,
+    pub easing: Easing,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Sequence {
+    keyframes: ArrayVec<Keyframe, MAX_KEYFRAMES>,
+    /// How many times the sequence plays, or 0 to repeat it until the LED is set to something
+    /// else.
+    pub loop_count: u8,
+}
+
+impl Sequence {
+    pub fn new(loop_count: u8) -> Self {
+        Self { keyframes: ArrayVec::new(), loop_count }
+    }
+
+    /// Add a keyframe, returning `false` if the sequence already has `MAX_KEYFRAMES`.
+    pub fn push(&mut self, keyframe: Keyframe) -> bool {
+        self.keyframes.try_push(keyframe).is_ok()
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// The commands that store the sequence in `slot`.
+    pub fn upload(&self, slot: u8) -> impl Iterator<Item = Command> + '_ {
+        let begin = Command::SequenceBegin {
+            slot,
+            keyframe_count: self.keyframes.len() as u8,
+            loop_count: self.loop_count,
+        };
+        let keyframes = self.keyframes.iter().enumerate().map(move |(index, &keyframe)| {
+            Command::SequenceKeyframe { slot, index: index as u8, keyframe }
+        });
+        core::iter::once(begin).chain(keyframes)
+    }
+
+    /// The color `elapsed_ms` after the sequence started playing, or `None` once it has
+    /// finished. Each loop starts from the color of the last keyframe, so loops are seamless.
+    pub fn color_at(&self, elapsed_ms: u32) -> Option<Rgb> {
+        let loop_ms: u32 =
+            self.keyframes.iter().map(|keyframe| u32::from(keyframe.duration_ms)).sum();
+        if loop_ms == 0 {
+            return None;
+        }
+        if self.loop_count > 0 && elapsed_ms / loop_ms >= u32::from(self.loop_count) {
+            return None;
+        }
+
+        let mut remaining_ms = elapsed_ms % loop_ms;
+        let mut from = self.keyframes.last()?.color;
+        for keyframe in &self.keyframes {
+            let duration_ms = u32::from(keyframe.duration_ms);
+            if remaining_ms < duration_ms {
+                let progress = (remaining_ms * u32::from(u16::MAX) / duration_ms) as u16;
+                let eased = u32::from(keyframe.easing.ease(progress));
+                let channel = |from: u8, to: u8| {
+                    Fade::new(from.into(), to.into(), u16::MAX.into()).value_at(eased) as u8
+                };
+                return Some(Rgb::new(
+                    channel(from.r, keyframe.color.r),
+                    channel(from.g, keyframe.color.g),
+                    channel(from.b, keyframe.color.b),
+                ));
+            }
+            remaining_ms -= duration_ms;
+            from = keyframe.color;
+        }
+        unreachable!("the remaining time is less than the length of a loop")
+    }
+}
+
+/// The panel's side of an upload, which collects keyframes until the sequence is complete.
+#[derive(Debug, Clone)]
+pub struct SequenceUpload {
+    slot: u8,
+    keyframe_count: u8,
+    sequence: Sequence,
+}
+
+impl SequenceUpload {
+    /// Start an upload from a `SequenceBegin`. Returns `None` if the sequence would have more
+    /// than `MAX_KEYFRAMES`.
+    pub fn begin(slot: u8, keyframe_count: u8, loop_count: u8) -> Option<Self> {
+        (usize::from(keyframe_count) <= MAX_KEYFRAMES).then(|| Self {
+            slot,
+            keyframe_count,
+            sequence: Sequence::new(loop_count),
+        })
+    }
+
+    pub fn slot(&self) -> u8 {
+        self.slot
+    }
+
+    /// Add the keyframe from a `SequenceKeyframe`. Keyframes for other slots or out of order are
+    /// ignored.
+    pub fn add(&mut self, slot: u8, index: u8, keyframe: Keyframe) {
+        if slot == self.slot
+            && usize::from(index) == self.sequence.keyframes.len()
+            && !self.is_complete()
+        {
+            self.sequence.push(keyframe);
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.sequence.keyframes.len() == usize::from(self.keyframe_count)
+    }
+
+    /// The uploaded sequence, once every keyframe has arrived.
+    pub fn finish(self) -> Option<Sequence> {
+        self.is_complete().then_some(self.sequence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgb = Rgb::new(255, 0, 0);
+
+    #[test]
+    fn eases() {
+        for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            assert_eq!(easing.ease(0), 0);
+            assert_eq!(easing.ease(u16::MAX), u16::MAX);
+        }
+        assert_eq!(Easing::Linear.ease(0x8000), 0x8000);
+        assert!(Easing::EaseIn.ease(0x8000) < 0x8000);
+        assert!(Easing::EaseOut.ease(0x8000) > 0x8000);
+        assert_eq!(Easing::EaseInOut.ease(0x8000), 0x8000);
+        assert_eq!(Easing::Step.ease(u16::MAX - 1), 0);
+    }
+
+    #[test]
+    fn plays_keyframes() {
+        let mut sequence = Sequence::new(2);
+        sequence.push(Keyframe { color: RED, duration_ms: 100, easing: Easing::Linear });
+        sequence.push(Keyframe { color: Rgb::BLACK, duration_ms: 100, easing: Easing::Step });
+
+        assert_eq!(sequence.color_at(0), Some(Rgb::BLACK));
+        assert_eq!(sequence.color_at(50), Some(Rgb::new(127, 0, 0)));
+        assert_eq!(sequence.color_at(150), Some(RED));
+        assert_eq!(sequence.color_at(250), Some(Rgb::new(127, 0, 0)));
+        assert_eq!(sequence.color_at(400), None);
+
+        sequence.loop_count = 0;
+        assert_eq!(sequence.color_at(100_050), Some(Rgb::new(127, 0, 0)));
+        assert_eq!(Sequence::new(0).color_at(0), None);
+    }
+
+    #[test]
+    fn uploads() {
+        let mut sequence = Sequence::new(1);
+        sequence.push(Keyframe { color: RED, duration_ms: 100, easing: Easing::EaseInOut });
+        sequence.push(Keyframe { color: Rgb::BLACK, duration_ms: 200, easing: Easing::Linear });
+
+        let mut upload = None;
+        for command in sequence.upload(3) {
+            match command {
+                Command::SequenceBegin { slot, keyframe_count, loop_count } => {
+                    upload = SequenceUpload::begin(slot, keyframe_count, loop_count)
+                },
+                Command::SequenceKeyframe { slot, index, keyframe } => {
+                    let upload = upload.as_mut().unwrap();
+                    assert!(!upload.is_complete());
+                    upload.add(slot, index, keyframe);
+                    // Repeats are ignored.
+                    upload.add(slot, index, keyframe);
+                },
+                _ => unreachable!(),
+            }
+        }
+
+        let upload = upload.unwrap();
+        assert_eq!(upload.slot(), 3);
+        assert_eq!(upload.finish(), Some(sequence));
+        assert!(SequenceUpload::begin(0, MAX_KEYFRAMES as u8 + 1, 0).is_none());
+    }
+}
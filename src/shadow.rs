@@ -0,0 +1,191 @@
+//! A host-side copy of the state last sent to the panel, used to turn the state an application
+//! wants into the fewest commands that get the panel there.
+
+use crate::{color::LedConfig, Command, Target};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct LightSetpoint {
+    pub brightness: u16,
+    pub temperature: u16,
+}
+
+#[derive(Debug, Default)]
+pub struct Shadow {
+    lights: BTreeMap<u8, LightSetpoint>,
+    led_config: Option<LedConfig>,
+    /// The lights as they were when each scene was saved.
+    scenes: BTreeMap<u8, BTreeMap<u8, LightSetpoint>>,
+}
+
+impl Shadow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The setpoint last sent to the light numbered `index`, or `None` if nothing has been sent
+    /// to it yet.
+    pub fn light(&self, index: u8) -> Option<LightSetpoint> {
+        self.lights.get(&index).copied()
+    }
+
+    /// The command that takes the light numbered `index` to `setpoint` over `transition_ms`, or
+    /// `None` if it's already there or `index` is above `Target::MAX_INDEX`, which no command can
+    /// address. The setpoint is recorded as sent.
+    ///
+    /// A change to only one of the values sends just that value. When both change, or the
+    /// light's state is unknown, they're sent together as a `Light` so the light never shows
+    /// the new brightness at the old temperature.
+    pub fn set_light(
+        &mut self,
+        index: u8,
+        setpoint: LightSetpoint,
+        transition_ms: u16,
+    ) -> Option<Command> {
+        let target = Target::single(index)?;
+        let previous = self.lights.insert(index, setpoint);
+        let LightSetpoint { brightness, temperature } = setpoint;
+
+        let brightness_changed = previous.is_none_or(|previous| previous.brightness != brightness);
+        let temperature_changed =
+            previous.is_none_or(|previous| previous.temperature != temperature);
+
+        match (brightness_changed, temperature_changed) {
+            (true, true) => Some(Command::Light { target, brightness, temperature, transition_ms }),
+            (true, false) if transition_ms == 0 => {
+                Some(Command::Brightness { target, value: brightness })
+            },
+            (true, false) => {
+                Some(Command::BrightnessFade { target, value: brightness, transition_ms })
+            },
+            (false, true) if transition_ms == 0 => {
+                Some(Command::Temperature { target, value: temperature })
+            },
+            (false, true) => {
+                Some(Command::TemperatureFade { target, value: temperature, transition_ms })
+            },
+            (false, false) => None,
+        }
+    }
+
+    /// The LED configuration last sent, or `None` if none has been sent yet.
+    pub fn led_config(&self) -> Option<LedConfig> {
+        self.led_config
+    }
+
+    /// The command that changes the LED configuration to `config`, or `None` if it already is.
+    /// It's tracked apart from the LED's color, so dimming never resends the color.
+    pub fn set_led_config(&mut self, config: LedConfig) -> Option<Command> {
+        (self.led_config.replace(config) != Some(config)).then(|| Command::led_config(config))
+    }
+
+    /// The command that saves the current state as scene `id`, which is remembered so recalling
+    /// it later keeps the shadow in step with the panel.
+    pub fn save_scene(&mut self, id: u8) -> Command {
+        self.scenes.insert(id, self.lights.clone());
+        Command::SaveScene { id }
+    }
+
+    /// The command that recalls scene `id`. Unless the scene was saved through this shadow, what
+    /// the lights show afterwards is unknown, so their next updates send their full state.
+    pub fn recall_scene(&mut self, id: u8, transition_ms: u16) -> Command {
+        self.lights = self.scenes.get(&id).cloned().unwrap_or_default();
+        Command::RecallScene { id, transition_ms }
+    }
+
+    /// Forget everything sent so far, e.g. after the panel restarts, so the next update of each
+    /// light and the LED configuration sends its full state. Saved scenes are kept, since the
+    /// panel keeps them too.
+    pub fn clear(&mut self) {
+        self.lights.clear();
+        self.led_config = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sends_only_what_changed() {
+        let mut shadow = Shadow::new();
+        let setpoint = LightSetpoint { brightness: 1200, temperature: 4000 };
+
+        assert_eq!(
+            shadow.set_light(0, setpoint, 0),
+            Some(Command::Light {
+                target: Target::single(0).unwrap(),
+                brightness: 1200,
+                temperature: 4000,
+                transition_ms: 0
+            })
+        );
+        assert_eq!(shadow.set_light(0, setpoint, 0), None);
+        assert_eq!(
+            shadow.set_light(0, LightSetpoint { brightness: 1300, ..setpoint }, 0),
+            Some(Command::Brightness { target: Target::single(0).unwrap(), value: 1300 })
+        );
+        assert_eq!(
+            shadow.set_light(0, LightSetpoint { brightness: 1300, temperature: 3000 }, 500),
+            Some(Command::TemperatureFade {
+                target: Target::single(0).unwrap(),
+                value: 3000,
+                transition_ms: 500
+            })
+        );
+        assert_eq!(
+            shadow.set_light(0, LightSetpoint { brightness: 0, temperature: 2000 }, 500),
+            Some(Command::Light {
+                target: Target::single(0).unwrap(),
+                brightness: 0,
+                temperature: 2000,
+                transition_ms: 500
+            })
+        );
+        assert_eq!(shadow.light(0), Some(LightSetpoint { brightness: 0, temperature: 2000 }));
+        assert_eq!(shadow.light(1), None);
+    }
+
+    #[test]
+    fn resends_full_state_after_clear() {
+        let mut shadow = Shadow::new();
+        let setpoint = LightSetpoint { brightness: 1, temperature: 2 };
+        shadow.set_light(0, setpoint, 0);
+        shadow.set_led_config(LedConfig::default());
+        shadow.clear();
+
+        assert!(matches!(shadow.set_light(0, setpoint, 0), Some(Command::Light { .. })));
+        assert!(shadow.set_led_config(LedConfig::default()).is_some());
+    }
+
+    #[test]
+    fn follows_scenes() {
+        let mut shadow = Shadow::new();
+        let meeting = LightSetpoint { brightness: 4000, temperature: 4000 };
+        let night = LightSetpoint { brightness: 100, temperature: 2000 };
+        shadow.set_light(0, meeting, 0);
+        assert_eq!(shadow.save_scene(1), Command::SaveScene { id: 1 });
+        shadow.set_light(0, night, 0);
+
+        assert_eq!(shadow.recall_scene(1, 500), Command::RecallScene { id: 1, transition_ms: 500 });
+        assert_eq!(shadow.light(0), Some(meeting));
+        assert_eq!(shadow.set_light(0, meeting, 0), None);
+
+        shadow.recall_scene(2, 0);
+        assert_eq!(shadow.light(0), None);
+    }
+
+    #[test]
+    fn sends_led_config_changes() {
+        let mut shadow = Shadow::new();
+        let night = LedConfig { global_brightness: 16, gamma: LedConfig::STANDARD_GAMMA };
+
+        assert_eq!(
+            shadow.set_led_config(night),
+            Some(Command::LedConfig { global_brightness: 16, gamma: 22 })
+        );
+        assert_eq!(shadow.set_led_config(night), None);
+        assert_eq!(shadow.led_config(), Some(night));
+        assert_eq!(shadow.light(0), None);
+    }
+}
@@ -0,0 +1,84 @@
+//! A blocking reader for host applications that wait for particular reports.
+
+use crate::{Report, ReportReader, MAX_SERIAL_MESSAGE_LEN};
+use std::{
+    collections::VecDeque,
+    io::{self, Read},
+    time::Instant,
+};
+
+const REPORT_QUEUE_SIZE: usize = 16;
+
+/// Reads reports from a transport one at a time, up to a deadline.
+///
+/// The transport needs a read timeout (e.g. `TTYPort::set_timeout()`) for deadlines to be
+/// noticed while the panel is quiet.
+pub struct ReportStream<'a, T> {
+    transport: &'a mut T,
+    reader: ReportReader,
+    read_buf: [u8; MAX_SERIAL_MESSAGE_LEN],
+    pending: VecDeque<Report>,
+}
+
+impl<'a, T: Read> ReportStream<'a, T> {
+    pub fn new(transport: &'a mut T) -> Self {
+        Self {
+            transport,
+            reader: ReportReader::new(),
+            read_buf: [0u8; MAX_SERIAL_MESSAGE_LEN],
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// The next report, or `None` once `deadline` passes.
+    pub fn next(&mut self, deadline: Instant) -> io::Result<Option<Report>> {
+        while self.pending.is_empty() {
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            self.read()?;
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    /// Discard the reports that have already arrived, i.e. until a read times out or `deadline`
+    /// passes, so that later waits only see reports sent from now on.
+    pub fn discard_pending(&mut self, deadline: Instant) -> io::Result<()> {
+        self.pending.clear();
+        while Instant::now() < deadline && self.read()? {
+            self.pending.clear();
+        }
+        Ok(())
+    }
+
+    /// Read once from the transport, returning `false` if the read timed out.
+    fn read(&mut self) -> io::Result<bool> {
+        let count = match self.transport.read(&mut self.read_buf) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(count) => count,
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let reports = self
+            .reader
+            .process_bytes::<REPORT_QUEUE_SIZE>(&self.read_buf[..count])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.pending.extend(reports);
+        Ok(true)
+    }
+
+    /// Wait for a report matching `predicate`, returning whether one arrived before `deadline`.
+    /// Other reports are discarded.
+    pub fn wait_for(
+        &mut self,
+        deadline: Instant,
+        predicate: impl Fn(&Report) -> bool,
+    ) -> io::Result<bool> {
+        while let Some(report) = self.next(deadline)? {
+            if predicate(&report) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
@@ -0,0 +1,131 @@
+//! Keeping track of the panel's health from the `FanStatus` and `Thermal` reports it sends, so
+//! monitoring can alert when a fan fails before the hardware overheats.
+//!
+//! The panel sends them on request with `Command::GetFanStatus` and `Command::GetThermal`, or
+//! every `ConfigKey::FanStatusInterval` and `ConfigKey::ThermalInterval` once those are set.
+
+use crate::{
+    units::{Duty, Rpm},
+    Address, Report,
+};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// The last status a fan reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FanStatus {
+    pub rpm: Rpm,
+    pub duty: Duty,
+    pub stalled: bool,
+    pub received: Instant,
+}
+
+/// The last temperature a sensor reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reading {
+    pub centi_celsius: i16,
+    pub received: Instant,
+}
+
+/// A change in health that monitoring should hear about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alert {
+    FanStalled(u8),
+    FanRecovered(u8),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Telemetry {
+    fans: BTreeMap<u8, FanStatus>,
+    sensors: BTreeMap<u8, Reading>,
+}
+
+impl Telemetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a `FanStatus` or `Thermal` report received at `now`, returning an alert if a fan
+    /// stalled or recovered since its last report. Other reports are ignored.
+    pub fn add(&mut self, report: &Report, now: Instant) -> Option<Alert> {
+        if let Report::Thermal { sensor, centi_celsius } = *report {
+            self.sensors.insert(sensor, Reading { centi_celsius, received: now });
+            return None;
+        }
+        let Report::FanStatus { target, rpm, duty, stalled } = *report else {
+            return None;
+        };
+        let Address::Single(index) = target.address() else {
+            return None;
+        };
+
+        let status = FanStatus { rpm: Rpm(rpm), duty: Duty(duty), stalled, received: now };
+        let was_stalled = self.fans.insert(index, status).is_some_and(|previous| previous.stalled);
+        match (was_stalled, stalled) {
+            (false, true) => Some(Alert::FanStalled(index)),
+            (true, false) => Some(Alert::FanRecovered(index)),
+            _ => None,
+        }
+    }
+
+    pub fn fan(&self, index: u8) -> Option<&FanStatus> {
+        self.fans.get(&index)
+    }
+
+    /// Every fan that has reported, by index.
+    pub fn fans(&self) -> impl Iterator<Item = (u8, &FanStatus)> {
+        self.fans.iter().map(|(&index, status)| (index, status))
+    }
+
+    pub fn sensor(&self, sensor: u8) -> Option<&Reading> {
+        self.sensors.get(&sensor)
+    }
+
+    /// Every sensor that has reported, by number.
+    pub fn sensors(&self) -> impl Iterator<Item = (u8, &Reading)> {
+        self.sensors.iter().map(|(&sensor, reading)| (sensor, reading))
+    }
+
+    /// The fans that haven't reported for longer than `max_age` at `now`, e.g. because the
+    /// panel stopped sending its periodic reports.
+    pub fn silent_fans(&self, now: Instant, max_age: Duration) -> impl Iterator<Item = u8> + '_ {
+        self.fans
+            .iter()
+            .filter(move |(_, status)| now.saturating_duration_since(status.received) > max_age)
+            .map(|(&index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Target;
+
+    #[test]
+    fn alerts_on_stalls() {
+        let status = |index, rpm, stalled| Report::FanStatus {
+            target: Target::single(index).unwrap(),
+            rpm,
+            duty: u16::MAX,
+            stalled,
+        };
+        let start = Instant::now();
+        let mut telemetry = Telemetry::new();
+
+        assert_eq!(telemetry.add(&status(0, 1800, false), start), None);
+        assert_eq!(telemetry.add(&status(1, 0, true), start), Some(Alert::FanStalled(1)));
+        assert_eq!(telemetry.add(&status(1, 0, true), start), None);
+        assert_eq!(telemetry.add(&Report::Press, start), None);
+
+        let later = start + Duration::from_secs(10);
+        assert_eq!(telemetry.add(&status(1, 1750, false), later), Some(Alert::FanRecovered(1)));
+        assert_eq!(telemetry.fan(1).map(|fan| fan.rpm), Some(Rpm(1750)));
+        assert_eq!(telemetry.silent_fans(later, Duration::from_secs(5)).collect::<Vec<_>>(), [0]);
+
+        let thermal = Report::Thermal { sensor: 2, centi_celsius: 4150 };
+        assert_eq!(telemetry.add(&thermal, later), None);
+        assert_eq!(telemetry.sensor(2).map(|reading| reading.centi_celsius), Some(4150));
+    }
+}
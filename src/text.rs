@@ -0,0 +1,582 @@
+//! A plain-text syntax for commands, shared by the `panel` tool and scripts.
+//!
+//! ```text
+//! led 255 0 0 --breathing 4000
+//! led red --blink 250 750
+//! led #ff8000 --dial-turn
+//! led red
+//! led 2700K
+//! led-config 32 2.2
+//! brightness 0 1200
+//! temperature 1 4000K --fade 500
+//! light 0 1200 4000K
+//! fan all 50%
+//! fan-curve all 0 30:20 60:100
+//! pixels 0 12 blue
+//! play 3
+//! recall-scene 2 --fade 1500
+//! set-config default-led-color orange
+//! set-config offline-fan-speed 80%
+//! offline-led red --blink 250 750
+//! save-config
+//! bootload
+//! ```
+//!
+//! A `<target>` is a number from 0 to 127, `front` or `back` for the well-known lights, `all`,
+//! or numbers from 0 to 6 joined by commas (e.g. `0,1`) to address several targets at once. A
+//! trailing comma (e.g. `0,`) makes a set of one target, which is sent differently from the
+//! target on its own.
+//! Temperatures are in Kelvin, and fan speeds can be given as a percentage of full speed.
+
+use crate::{
+    color::Rgb,
+    config::{Config, ConfigKey},
+    fan::{CurvePoint, CurvePoints, MAX_CURVE_POINTS},
+    units::{Duty, Kelvin},
+    Address, Command, PulseMode, Target,
+};
+use std::{fmt, num::NonZeroU16, str::FromStr};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl ParseError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A summary of the syntax, for usage messages.
+pub const SYNTAX: &str = "\
+    led <r> <g> <b> [<pulse mode>]
+    led #<rrggbb> [<pulse mode>]
+    led <color name> [<pulse mode>]
+    led <kelvin>K [<pulse mode>]
+    brightness <target> <value> [--fade <transition_ms>]
+    temperature <target> <kelvin>[K] [--fade <transition_ms>]
+    light <target> <brightness> <kelvin>[K] [--fade <transition_ms>]
+    fan <target> <duty> | <percent>%
+    pixels <first pixel> <count> <r> <g> <b> | #<rrggbb> | <color name> | <kelvin>K
+    led-config <global brightness> <gamma>
+    play <sequence slot>
+    save-scene <scene>
+    recall-scene <scene> [--fade <transition_ms>]
+    set-config <setting> <value>
+    get-config <setting>
+    save-config
+    factory-reset
+    offline-led <color> [<pulse mode>]
+    heartbeat
+    fan-status [<target>]
+    fan-curve <target> <sensor> [<celsius>:<percent>...]
+    thermal
+    bootload
+
+    where <pulse mode> is one of
+        --solid | --dial-turn | --dial-ring | --double-pulse | --breathing <interval_ms>
+        --blink <on_ms> <off_ms> | --fade-to <ms> | --color-cycle <period_ms>
+
+    and <setting> is one of
+        dial-sensitivity | default-led-color | power-on-brightness | power-on-fan-speed
+        heartbeat-interval | offline-brightness | offline-fade | offline-fan-speed
+        fan-status-interval | thermal-interval
+    where colors are given like for pixels, fan speeds like for fan, and times in ms";
+
+/// Colors that can be given to `led` by name.
+pub const COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("off", (0, 0, 0)),
+    ("white", (255, 255, 255)),
+    ("red", (255, 0, 0)),
+    ("green", (0, 255, 0)),
+    ("blue", (0, 0, 255)),
+    ("yellow", (255, 255, 0)),
+    ("cyan", (0, 255, 255)),
+    ("magenta", (255, 0, 255)),
+    ("orange", (255, 128, 0)),
+];
+
+/// Parse a number, naming `what` it is in the error.
+pub fn parse_number<T: FromStr>(word: Option<&str>, what: &str) -> Result<T, ParseError> {
+    let word = word.ok_or_else(|| ParseError::new(format!("missing {what}")))?;
+    word.parse().map_err(|_| ParseError::new(format!("invalid {what} \"{word}\"")))
+}
+
+/// Parse a color temperature in Kelvin, with or without a `K` suffix.
+pub fn parse_temperature(word: Option<&str>) -> Result<u16, ParseError> {
+    let word = word.ok_or_else(|| ParseError::new("missing temperature"))?;
+    let kelvin = word.trim_end_matches(['K', 'k']);
+    kelvin.parse().ok().and_then(Kelvin::new).map(|kelvin| kelvin.get()).ok_or_else(|| {
+        ParseError::new(format!(
+            "invalid temperature \"{word}\", expected {} to {} Kelvin",
+            Kelvin::MIN.get(),
+            Kelvin::MAX.get()
+        ))
+    })
+}
+
+/// Parse a gamma like `2.2` into tenths.
+pub fn parse_gamma(word: Option<&str>) -> Result<u8, ParseError> {
+    let gamma: f32 = parse_number(word, "gamma")?;
+    let tenths = (gamma * 10.0).round();
+    if (1.0..=f32::from(u8::MAX)).contains(&tenths) {
+        Ok(tenths as u8)
+    } else {
+        Err(ParseError::new(format!("invalid gamma \"{}\"", word.unwrap_or_default())))
+    }
+}
+
+/// Parse a fan speed, either a raw duty cycle or a percentage like `50%`.
+pub fn parse_fan_speed(word: Option<&str>) -> Result<u16, ParseError> {
+    match word.and_then(|word| word.strip_suffix('%')) {
+        Some(percent) => percent
+            .parse()
+            .ok()
+            .and_then(Duty::from_percent)
+            .map(|duty| duty.0)
+            .ok_or_else(|| ParseError::new(format!("invalid fan speed \"{percent}%\""))),
+        None => parse_number(word, "fan speed"),
+    }
+}
+
+/// Parse a target, see the module documentation for the forms it takes.
+pub fn parse_target(word: Option<&str>) -> Result<Target, ParseError> {
+    let word = word.ok_or_else(|| ParseError::new("missing target"))?;
+    let invalid = || ParseError::new(format!("invalid target \"{word}\""));
+
+    match word {
+        "front" => Ok(Target::FRONT_LIGHTS),
+        "back" => Ok(Target::BACK_LIGHTS),
+        "all" => Ok(Target::ALL),
+        _ if word.contains(',') => {
+            let mut mask = 0u8;
+            for index in word.strip_suffix(',').unwrap_or(word).split(',') {
+                let index: u8 = index.parse().map_err(|_| invalid())?;
+                if index > 6 {
+                    return Err(invalid());
+                }
+                mask |= 1 << index;
+            }
+            Target::mask(mask).ok_or_else(invalid)
+        },
+        _ => word.parse().ok().and_then(Target::single).ok_or_else(invalid),
+    }
+}
+
+/// Format a target the way `parse_target()` reads it.
+pub fn format_target(target: Target) -> String {
+    match target.address() {
+        Address::Single(index) => index.to_string(),
+        Address::Mask(mask) => {
+            let indices: Vec<_> = (0..7)
+                .filter(|index| mask & (1 << index) != 0)
+                .map(|index| index.to_string())
+                .collect();
+            // A lone index would be read back as a single target.
+            if indices.len() == 1 {
+                format!("{},", indices[0])
+            } else {
+                indices.join(",")
+            }
+        },
+        Address::All => "all".to_string(),
+    }
+}
+
+/// Parse the optional `--fade <transition_ms>` after a brightness, temperature, light or
+/// recalled scene.
+fn parse_fade<'a>(
+    args: &mut impl Iterator<Item = &'a str>,
+    name: &str,
+) -> Result<Option<u16>, ParseError> {
+    match args.next() {
+        None => Ok(None),
+        Some("--fade" | "fade") => parse_number(args.next(), "transition time").map(Some),
+        Some(extra) => Err(ParseError::new(format!("unexpected \"{extra}\" after {name}"))),
+    }
+}
+
+/// Parse a color given as `<r> <g> <b>`, `#<rrggbb>`, a name from `COLORS` or `<kelvin>K`.
+fn parse_color<'a>(args: &mut impl Iterator<Item = &'a str>) -> Result<Rgb, ParseError> {
+    let first = args.next();
+    if let Some(&(_, (r, g, b))) = COLORS.iter().find(|(name, _)| Some(*name) == first) {
+        Ok(Rgb::new(r, g, b))
+    } else if let Some(hex) = first.and_then(|first| first.strip_prefix('#')) {
+        let rgb = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)
+            .ok_or_else(|| ParseError::new(format!("invalid color \"#{hex}\"")))?;
+        Ok(Rgb::new((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+    } else if first.is_some_and(|first| first.ends_with('K')) {
+        let kelvin = Kelvin::saturating(parse_temperature(first)?);
+        Ok(Rgb::from_kelvin(kelvin).gamma_corrected())
+    } else {
+        Ok(Rgb::new(
+            parse_number(first, "red value")?,
+            parse_number(args.next(), "green value")?,
+            parse_number(args.next(), "blue value")?,
+        ))
+    }
+}
+
+/// Parse an optional pulse mode, which reads like a flag on the command line and like a word in
+/// scripts.
+fn parse_pulse_mode<'a>(args: &mut impl Iterator<Item = &'a str>) -> Result<PulseMode, ParseError> {
+    Ok(match args.next().map(|mode| mode.trim_start_matches("--")) {
+        None | Some("solid") => PulseMode::Solid,
+        Some("dial-turn") => PulseMode::DialTurn,
+        Some("breathing") => {
+            let interval_ms: NonZeroU16 = parse_number(args.next(), "breathing interval")?;
+            PulseMode::Breathing { interval_ms }
+        },
+        Some("blink") => PulseMode::Blink {
+            on_ms: parse_number(args.next(), "blink on time")?,
+            off_ms: parse_number(args.next(), "blink off time")?,
+        },
+        Some("fade-to") => PulseMode::FadeTo { ms: parse_number(args.next(), "fade time")? },
+        Some("color-cycle") => {
+            PulseMode::ColorCycle { period_ms: parse_number(args.next(), "color cycle period")? }
+        },
+        Some("double-pulse") => PulseMode::DoublePulse,
+        Some("dial-ring") => PulseMode::DialRing,
+        Some(mode) => return Err(ParseError::new(format!("unknown pulse mode \"{mode}\""))),
+    })
+}
+
+/// Parse the points of a fan curve, each given as `<celsius>:<percent>`.
+fn parse_curve_points<'a>(args: impl Iterator<Item = &'a str>) -> Result<CurvePoints, ParseError> {
+    let points = args
+        .map(|word| {
+            let (celsius, percent) = word.split_once(':').unwrap_or((word, ""));
+            Ok(CurvePoint {
+                celsius: parse_number(Some(celsius), "curve temperature")?,
+                percent: parse_number(Some(percent.trim_end_matches('%')), "curve percentage")?,
+            })
+        })
+        .collect::<Result<Vec<_>, ParseError>>()?;
+    CurvePoints::new(&points).ok_or_else(|| {
+        ParseError::new(format!(
+            "invalid fan curve, expected up to {MAX_CURVE_POINTS} points of rising temperature \
+             at up to 100%"
+        ))
+    })
+}
+
+/// Parse the name of a setting, see `ConfigKey::name`.
+pub fn parse_config_key(word: Option<&str>) -> Result<ConfigKey, ParseError> {
+    let word = word.ok_or_else(|| ParseError::new("missing setting"))?;
+    ConfigKey::ALL
+        .iter()
+        .copied()
+        .find(|key| key.name() == word)
+        .ok_or_else(|| ParseError::new(format!("unknown setting \"{word}\"")))
+}
+
+/// Parse the value of a setting, as it's sent in `SetConfig`.
+fn parse_config_value<'a>(
+    key: ConfigKey,
+    args: &mut impl Iterator<Item = &'a str>,
+) -> Result<u32, ParseError> {
+    let mut config = Config::default();
+    if key == ConfigKey::DefaultLedColor {
+        config.default_led_color = parse_color(args)?;
+        return Ok(config.get(key));
+    }
+
+    let word = args.next();
+    let value = match key {
+        ConfigKey::PowerOnFanSpeed | ConfigKey::OfflineFanSpeed => parse_fan_speed(word)?.into(),
+        _ => parse_number(word, key.name())?,
+    };
+    if config.set(key, value) {
+        Ok(value)
+    } else {
+        Err(ParseError::new(format!("invalid {} \"{}\"", key.name(), word.unwrap_or_default())))
+    }
+}
+
+/// Parse a command from its words, e.g. `["brightness", "0", "1200"]`.
+pub fn parse_command(words: &[&str]) -> Result<Command, ParseError> {
+    let (&name, args) = words.split_first().ok_or_else(|| ParseError::new("missing command"))?;
+    let mut args = args.iter().copied();
+
+    let command = match name {
+        "led" => {
+            let Rgb { r, g, b } = parse_color(&mut args)?;
+            Command::Led { r, g, b, pulse_mode: parse_pulse_mode(&mut args)? }
+        },
+        "offline-led" => {
+            let Rgb { r, g, b } = parse_color(&mut args)?;
+            Command::OfflineLed { r, g, b, pulse_mode: parse_pulse_mode(&mut args)? }
+        },
+        "brightness" => {
+            let target = parse_target(args.next())?;
+            let value = parse_number(args.next(), "brightness")?;
+            match parse_fade(&mut args, name)? {
+                Some(transition_ms) => Command::BrightnessFade { target, value, transition_ms },
+                None => Command::Brightness { target, value },
+            }
+        },
+        "temperature" => {
+            let target = parse_target(args.next())?;
+            let value = parse_temperature(args.next())?;
+            match parse_fade(&mut args, name)? {
+                Some(transition_ms) => Command::TemperatureFade { target, value, transition_ms },
+                None => Command::Temperature { target, value },
+            }
+        },
+        "light" => Command::Light {
+            target: parse_target(args.next())?,
+            brightness: parse_number(args.next(), "brightness")?,
+            temperature: parse_temperature(args.next())?,
+            transition_ms: parse_fade(&mut args, name)?.unwrap_or(0),
+        },
+        "fan" => Command::FanSpeed {
+            target: parse_target(args.next())?,
+            value: parse_fan_speed(args.next())?,
+        },
+        "pixels" => Command::Pixels {
+            start: parse_number(args.next(), "first pixel")?,
+            count: parse_number(args.next(), "pixel count")?,
+            color: parse_color(&mut args)?,
+        },
+        "led-config" => Command::LedConfig {
+            global_brightness: parse_number(args.next(), "global brightness")?,
+            gamma: parse_gamma(args.next())?,
+        },
+        "save-scene" => Command::SaveScene { id: parse_number(args.next(), "scene")? },
+        "recall-scene" => {
+            let id = parse_number(args.next(), "scene")?;
+            let transition_ms = parse_fade(&mut args, name)?.unwrap_or(0);
+            Command::RecallScene { id, transition_ms }
+        },
+        "set-config" => {
+            let key = parse_config_key(args.next())?;
+            Command::SetConfig { key, value: parse_config_value(key, &mut args)? }
+        },
+        "get-config" => Command::GetConfig { key: parse_config_key(args.next())? },
+        "save-config" => Command::SaveConfig,
+        "factory-reset" => Command::FactoryReset,
+        "heartbeat" => Command::Heartbeat,
+        "fan-curve" => Command::FanCurve {
+            target: parse_target(args.next())?,
+            sensor: parse_number(args.next(), "sensor")?,
+            points: parse_curve_points(args.by_ref())?,
+        },
+        "thermal" => Command::GetThermal,
+        "fan-status" => match args.next() {
+            None => Command::GetFanStatus { target: Target::ALL },
+            target => Command::GetFanStatus { target: parse_target(target)? },
+        },
+        "play" => Command::PlaySequence { slot: parse_number(args.next(), "sequence slot")? },
+        "bootload" => Command::Bootload,
+        _ => return Err(ParseError::new(format!("unknown command \"{name}\""))),
+    };
+
+    match args.next() {
+        Some(extra) => Err(ParseError::new(format!("unexpected \"{extra}\" after {name}"))),
+        None => Ok(command),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Command, ParseError> {
+        parse_command(&line.split_whitespace().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            parse("brightness 0 1200"),
+            Ok(Command::Brightness { target: Target::single(0).unwrap(), value: 1200 })
+        );
+        assert_eq!(
+            parse("temperature 1 4000 --fade 500"),
+            Ok(Command::TemperatureFade {
+                target: Target::single(1).unwrap(),
+                value: 4000,
+                transition_ms: 500
+            })
+        );
+        assert_eq!(
+            parse("light 0 1200 4000 --fade 300"),
+            Ok(Command::Light {
+                target: Target::single(0).unwrap(),
+                brightness: 1200,
+                temperature: 4000,
+                transition_ms: 300
+            })
+        );
+        assert_eq!(
+            parse("fan 1 600"),
+            Ok(Command::FanSpeed { target: Target::single(1).unwrap(), value: 600 })
+        );
+        assert_eq!(parse("bootload"), Ok(Command::Bootload));
+        assert_eq!(parse("play 3"), Ok(Command::PlaySequence { slot: 3 }));
+        assert_eq!(
+            parse("recall-scene 2 --fade 1500"),
+            Ok(Command::RecallScene { id: 2, transition_ms: 1500 })
+        );
+        assert_eq!(
+            parse("led-config 32 2.2"),
+            Ok(Command::LedConfig { global_brightness: 32, gamma: 22 })
+        );
+        assert!(parse("led-config 32 0").is_err());
+        assert_eq!(
+            parse("set-config default-led-color orange"),
+            Ok(Command::SetConfig { key: ConfigKey::DefaultLedColor, value: 0x00ff_8000 })
+        );
+        assert_eq!(
+            parse("set-config heartbeat-interval 500"),
+            Ok(Command::SetConfig { key: ConfigKey::HeartbeatInterval, value: 500 })
+        );
+        assert!(parse("set-config dial-sensitivity 0").is_err());
+        assert_eq!(
+            parse("set-config offline-fan-speed 100%"),
+            Ok(Command::SetConfig { key: ConfigKey::OfflineFanSpeed, value: 65535 })
+        );
+        assert_eq!(
+            parse("offline-led red --double-pulse"),
+            Ok(Command::OfflineLed { r: 255, g: 0, b: 0, pulse_mode: PulseMode::DoublePulse })
+        );
+        assert_eq!(parse("heartbeat"), Ok(Command::Heartbeat));
+        assert_eq!(parse("fan-status"), Ok(Command::GetFanStatus { target: Target::ALL }));
+        assert_eq!(
+            parse("fan-status 1"),
+            Ok(Command::GetFanStatus { target: Target::single(1).unwrap() })
+        );
+        assert_eq!(
+            parse("fan-curve all 0 30:20 60:100%"),
+            Ok(Command::FanCurve {
+                target: Target::ALL,
+                sensor: 0,
+                points: CurvePoints::new(&[
+                    CurvePoint { celsius: 30, percent: 20 },
+                    CurvePoint { celsius: 60, percent: 100 },
+                ])
+                .unwrap(),
+            })
+        );
+        assert_eq!(
+            parse("fan-curve 0 1"),
+            Ok(Command::FanCurve {
+                target: Target::single(0).unwrap(),
+                sensor: 1,
+                points: CurvePoints::default()
+            })
+        );
+        assert!(parse("fan-curve 0 1 60:100 30:20").is_err());
+        assert!(parse("fan-curve 0 1 60").is_err());
+        assert_eq!(parse("thermal"), Ok(Command::GetThermal));
+        assert_eq!(
+            parse("get-config power-on-brightness"),
+            Ok(Command::GetConfig { key: ConfigKey::PowerOnBrightness })
+        );
+        assert_eq!(
+            parse("pixels 0 24 #0000ff"),
+            Ok(Command::Pixels { start: 0, count: 24, color: Rgb::new(0, 0, 255) })
+        );
+        assert_eq!(
+            parse("brightness back 1200"),
+            Ok(Command::Brightness { target: Target::BACK_LIGHTS, value: 1200 })
+        );
+        assert_eq!(
+            parse("temperature front 2700K"),
+            Ok(Command::Temperature { target: Target::FRONT_LIGHTS, value: 2700 })
+        );
+        assert_eq!(
+            parse("fan 0 50%"),
+            Ok(Command::FanSpeed { target: Target::single(0).unwrap(), value: 32768 })
+        );
+        assert_eq!(parse("fan all 600"), Ok(Command::FanSpeed { target: Target::ALL, value: 600 }));
+        assert_eq!(
+            parse("fan 0,2 600"),
+            Ok(Command::FanSpeed { target: Target::mask(0b101).unwrap(), value: 600 })
+        );
+        assert_eq!(
+            parse("led 255 0 0 --breathing 4000"),
+            Ok(Command::Led {
+                r: 255,
+                g: 0,
+                b: 0,
+                pulse_mode: PulseMode::Breathing { interval_ms: NonZeroU16::new(4000).unwrap() },
+            })
+        );
+        assert_eq!(
+            parse("led #ff8000 --dial-turn"),
+            Ok(Command::Led { r: 255, g: 128, b: 0, pulse_mode: PulseMode::DialTurn })
+        );
+        assert_eq!(
+            parse("led red solid"),
+            Ok(Command::Led { r: 255, g: 0, b: 0, pulse_mode: PulseMode::Solid })
+        );
+        assert_eq!(
+            parse("led red --blink 250 750"),
+            Ok(Command::Led {
+                r: 255,
+                g: 0,
+                b: 0,
+                pulse_mode: PulseMode::Blink {
+                    on_ms: NonZeroU16::new(250).unwrap(),
+                    off_ms: NonZeroU16::new(750).unwrap(),
+                },
+            })
+        );
+        assert_eq!(
+            parse("led off color-cycle 6000"),
+            Ok(Command::Led {
+                r: 0,
+                g: 0,
+                b: 0,
+                pulse_mode: PulseMode::ColorCycle { period_ms: NonZeroU16::new(6000).unwrap() },
+            })
+        );
+        assert_eq!(
+            parse("led 2700K"),
+            Ok(Command::Led { r: 255, g: 99, b: 23, pulse_mode: PulseMode::Solid })
+        );
+    }
+
+    #[test]
+    fn formats_targets() {
+        for target in [
+            Target::single(100).unwrap(),
+            Target::single(0).unwrap(),
+            Target::mask(0b1011).unwrap(),
+            Target::mask(0b1).unwrap(),
+            Target::ALL,
+        ] {
+            assert_eq!(parse_target(Some(&format_target(target))), Ok(target));
+        }
+        assert_eq!(format_target(Target::mask(0b100).unwrap()), "2,");
+        assert!(parse_target(Some(",")).is_err());
+        assert!(parse_target(Some("0,,")).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_commands() {
+        assert!(parse("").is_err());
+        assert!(parse("brightness 0").is_err());
+        assert!(parse("brightness 0 70000").is_err());
+        assert!(parse("brightness 128 1200").is_err());
+        assert!(parse("brightness 0,7 1200").is_err());
+        assert!(parse("temperature 0 500").is_err());
+        assert!(parse("fan 0 101%").is_err());
+        assert!(parse("brightness 0 1200 --fade").is_err());
+        assert!(parse("brightness 0 1200 --breathing 500").is_err());
+        assert!(parse("led 255 0 0 --breathing 0").is_err());
+        assert!(parse("led #ff80").is_err());
+        assert!(parse("led purple").is_err());
+        assert!(parse("bootload now").is_err());
+    }
+}
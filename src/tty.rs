@@ -0,0 +1,129 @@
+//! Terminal plumbing shared by the command line tools.
+
+use serial_core::{BaudRate, SerialDevice, SerialPortSettings};
+use serial_unix::TTYPort;
+use std::{
+    ffi::CStr,
+    fs::{File, OpenOptions},
+    io,
+    os::unix::{
+        fs::OpenOptionsExt,
+        io::{AsRawFd, FromRawFd, RawFd},
+    },
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Open the serial port of a panel. Reads fail with `io::ErrorKind::TimedOut` if nothing
+/// arrives within `timeout`.
+pub fn open(path: &Path, timeout: Duration) -> io::Result<TTYPort> {
+    let mut tty = TTYPort::open(path)?;
+    tty.set_timeout(timeout)?;
+
+    // The panel firmware runs at 115200 baud.
+    // TODO: Remove this after switching to the native USB connection.
+    let mut tty_settings = tty.read_settings()?;
+    tty_settings.set_baud_rate(BaudRate::Baud115200)?;
+    tty.write_settings(&tty_settings)?;
+
+    Ok(tty)
+}
+
+/// A pseudo-terminal pair. The slave end behaves like the serial port of a real panel, so
+/// anything that can open a panel's TTY can open `path()` instead.
+pub struct Pty {
+    master: File,
+    // Held open so reads on the master don't fail with EIO while no client is connected.
+    _slave: File,
+    path: PathBuf,
+}
+
+impl Pty {
+    pub fn open() -> io::Result<Self> {
+        let fd = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let master = unsafe { File::from_raw_fd(fd) };
+
+        if unsafe { libc::grantpt(fd) } != 0 || unsafe { libc::unlockpt(fd) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let path = slave_path(fd)?;
+        let slave =
+            OpenOptions::new().read(true).write(true).custom_flags(libc::O_NOCTTY).open(&path)?;
+        make_raw(slave.as_raw_fd())?;
+
+        Ok(Self { master, _slave: slave, path })
+    }
+
+    /// The path of the slave end, to be opened by the host application.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The master end, which speaks the device side of the protocol.
+    pub fn master(&self) -> &File {
+        &self.master
+    }
+}
+
+/// The path of the slave end of the pseudo-terminal with master `fd`.
+#[cfg(target_os = "linux")]
+fn slave_path(fd: RawFd) -> io::Result<PathBuf> {
+    let mut name = [0 as libc::c_char; 128];
+    let result = unsafe { libc::ptsname_r(fd, name.as_mut_ptr(), name.len()) };
+    if result != 0 {
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    path_from_c(&name)
+}
+
+/// The path of the slave end of the pseudo-terminal with master `fd`. macOS has no
+/// `ptsname_r()`, but asks for a buffer of exactly 128 bytes here instead.
+#[cfg(target_os = "macos")]
+fn slave_path(fd: RawFd) -> io::Result<PathBuf> {
+    let mut name = [0 as libc::c_char; 128];
+    if unsafe { libc::ioctl(fd, libc::TIOCPTYGNAME as libc::c_ulong, name.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    path_from_c(&name)
+}
+
+/// The path of the slave end of the pseudo-terminal with master `fd`. `ptsname()` returns a
+/// static buffer, so calls are serialized and the name is copied out before the lock is released.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn slave_path(fd: RawFd) -> io::Result<PathBuf> {
+    static PTSNAME: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    let _guard = PTSNAME.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let name = unsafe { libc::ptsname(fd) };
+    if name.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    path_from_c(unsafe { std::slice::from_raw_parts(name, libc::strlen(name) + 1) })
+}
+
+fn path_from_c(name: &[libc::c_char]) -> io::Result<PathBuf> {
+    let path = unsafe { CStr::from_ptr(name.as_ptr()) }
+        .to_str()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(PathBuf::from(path))
+}
+
+/// Put a terminal into raw mode, so bytes pass through without line editing or echo.
+fn make_raw(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let mut termios = std::mem::zeroed();
+        if libc::tcgetattr(fd, &mut termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        libc::cfmakeraw(&mut termios);
+        if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! The physical meaning of the values sent in `Temperature` and `FanSpeed` commands, and
+//! conversions between them and the units people think in.
+//!
+//! On the wire, a color temperature is in Kelvin and a fan speed is a PWM duty cycle where
+//! `u16::MAX` is 100%.
+//!
+//! ```
+//! use panel_protocol::{units::{Duty, Kelvin}, Command, Target};
+//!
+//! let warm = Command::temperature(Target::FRONT_LIGHTS, Kelvin::new(2700).unwrap());
+//! assert_eq!(warm, Command::Temperature { target: Target::FRONT_LIGHTS, value: 2700 });
+//!
+//! let half = Command::fan_speed(Target::ALL, Duty::from_percent(50).unwrap());
+//! assert_eq!(half, Command::FanSpeed { target: Target::ALL, value: 32768 });
+//! ```
+
+use crate::{Command, Target};
+use core::convert::TryFrom;
+
+/// A color temperature.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Kelvin(u16);
+
+impl Kelvin {
+    pub const CANDLELIGHT: Kelvin = Kelvin(1900);
+    pub const DAYLIGHT: Kelvin = Kelvin(6500);
+    pub const MAX: Kelvin = Kelvin(40000);
+    pub const MIN: Kelvin = Kelvin(1000);
+    pub const NEUTRAL_WHITE: Kelvin = Kelvin(4000);
+    pub const WARM_WHITE: Kelvin = Kelvin(2700);
+
+    /// Returns `None` outside of `Kelvin::MIN..=Kelvin::MAX`.
+    pub fn new(kelvin: u16) -> Option<Self> {
+        (Self::MIN.0..=Self::MAX.0).contains(&kelvin).then_some(Self(kelvin))
+    }
+
+    /// Clamp `kelvin` into `Kelvin::MIN..=Kelvin::MAX`, e.g. for a value received off the wire.
+    pub fn saturating(kelvin: u16) -> Self {
+        Self(kelvin.clamp(Self::MIN.0, Self::MAX.0))
+    }
+
+    pub const fn get(&self) -> u16 {
+        self.0
+    }
+
+    /// The temperature in mireds (micro reciprocal degrees), which is closer to how people
+    /// perceive differences in color temperature.
+    pub fn to_mireds(&self) -> u16 {
+        ((1_000_000 + u32::from(self.0) / 2) / u32::from(self.0)) as u16
+    }
+
+    /// Returns `None` if `mireds` is outside of the range of `Kelvin`.
+    pub fn from_mireds(mireds: u16) -> Option<Self> {
+        if mireds == 0 {
+            return None;
+        }
+        let kelvin = (1_000_000 + u32::from(mireds) / 2) / u32::from(mireds);
+        u16::try_from(kelvin).ok().and_then(Self::new)
+    }
+}
+
+/// A fan's PWM duty cycle, from 0 (off) to `u16::MAX` (full speed).
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Duty(pub u16);
+
+impl Duty {
+    pub const FULL: Duty = Duty(u16::MAX);
+    pub const OFF: Duty = Duty(0);
+
+    /// Returns `None` above 100%.
+    pub fn from_percent(percent: u8) -> Option<Self> {
+        if percent > 100 {
+            return None;
+        }
+        Some(Self(((u32::from(percent) * u32::from(u16::MAX) + 50) / 100) as u16))
+    }
+
+    /// The duty cycle, rounded to the nearest percent.
+    pub fn percent(&self) -> u8 {
+        ((u32::from(self.0) * 100 + u32::from(u16::MAX) / 2) / u32::from(u16::MAX)) as u8
+    }
+
+    /// The duty cycle expected to spin a fan at `rpm`, assuming speed is proportional to duty up
+    /// to `max_rpm` at full duty. Speeds above `max_rpm` give full duty.
+    pub fn for_rpm(rpm: Rpm, max_rpm: Rpm) -> Self {
+        if max_rpm.0 == 0 || rpm >= max_rpm {
+            return Self::FULL;
+        }
+        Self((u32::from(rpm.0) * u32::from(u16::MAX) / u32::from(max_rpm.0)) as u16)
+    }
+}
+
+/// A fan's measured speed, in revolutions per minute.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rpm(pub u16);
+
+impl Rpm {
+    /// The speed of a fan from the number of tachometer pulses counted over `interval_ms`.
+    pub fn from_tachometer(pulse_count: u32, pulses_per_revolution: u8, interval_ms: u32) -> Self {
+        if pulses_per_revolution == 0 || interval_ms == 0 {
+            return Self(0);
+        }
+        let rpm = u64::from(pulse_count) * 60_000
+            / (u64::from(pulses_per_revolution) * u64::from(interval_ms));
+        Self(rpm.min(u64::from(u16::MAX)) as u16)
+    }
+}
+
+impl Command {
+    pub fn temperature(target: Target, temperature: Kelvin) -> Self {
+        Command::Temperature { target, value: temperature.get() }
+    }
+
+    pub fn temperature_fade(target: Target, temperature: Kelvin, transition_ms: u16) -> Self {
+        Command::TemperatureFade { target, value: temperature.get(), transition_ms }
+    }
+
+    pub fn light(target: Target, brightness: u16, temperature: Kelvin, transition_ms: u16) -> Self {
+        Command::Light { target, brightness, temperature: temperature.get(), transition_ms }
+    }
+
+    pub fn fan_speed(target: Target, duty: Duty) -> Self {
+        Command::FanSpeed { target, value: duty.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_kelvin() {
+        assert_eq!(Kelvin::new(999), None);
+        assert_eq!(Kelvin::new(6500), Some(Kelvin::DAYLIGHT));
+        assert_eq!(Kelvin::new(40001), None);
+        assert_eq!(Kelvin::saturating(0), Kelvin::MIN);
+        assert_eq!(Kelvin::saturating(u16::MAX), Kelvin::MAX);
+    }
+
+    #[test]
+    fn converts_mireds() {
+        assert_eq!(Kelvin::WARM_WHITE.to_mireds(), 370);
+        assert_eq!(Kelvin::from_mireds(370), Kelvin::new(2703));
+        assert_eq!(Kelvin::from_mireds(0), None);
+        assert_eq!(Kelvin::from_mireds(1001), None);
+    }
+
+    #[test]
+    fn converts_duty() {
+        assert_eq!(Duty::from_percent(0), Some(Duty::OFF));
+        assert_eq!(Duty::from_percent(100), Some(Duty::FULL));
+        assert_eq!(Duty::from_percent(101), None);
+        for percent in 0..=100 {
+            assert_eq!(Duty::from_percent(percent).unwrap().percent(), percent);
+        }
+
+        assert_eq!(Duty::for_rpm(Rpm(900), Rpm(1800)), Duty(32767));
+        assert_eq!(Duty::for_rpm(Rpm(2000), Rpm(1800)), Duty::FULL);
+    }
+
+    #[test]
+    fn counts_tachometer_pulses() {
+        // Two pulses per revolution, 60 pulses in 1 second is 30 revolutions per second.
+        assert_eq!(Rpm::from_tachometer(60, 2, 1000), Rpm(1800));
+        assert_eq!(Rpm::from_tachometer(60, 0, 1000), Rpm(0));
+    }
+}